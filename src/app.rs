@@ -4,17 +4,43 @@ use graphics::*;
 use Grid;
 use Title;
 use TitleEvent;
+use title_page::{Layout, LAYOUT_WIDTH, LAYOUT_HEIGHT};
 use GameEvent;
+use grid::{GameSettings, LoadError};
+use pattern_db::PatternDbs;
+use board::{self, Direction, GoalLayout, Variant};
+use score::{self, Score, ScoreBook, ScoreKey};
+use leaderboard::{Leaderboard, LeaderboardEvent};
+use save::SavedGame;
+use daily::{self, DailyBook};
+use std::mem;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use tinyfiledialogs::{self, MessageBoxIcon};
 
 pub enum State {
     Game(Grid),
-    Title(Title)
+    Title(Title),
+    Leaderboard(Leaderboard)
 }
 
+//seconds a direction key has to be held before it starts
+//repeating, then seconds between each repeated move
+const KEY_REPEAT_DELAY: f64 = 0.3;
+const KEY_REPEAT_RATE: f64 = 0.1;
+
 pub struct App {
     gl : GlGraphics,
     state: State ,
-    high_score: u32
+    scores: ScoreBook,
+    daily: DailyBook,
+    //the title is put aside while the leaderboard is open
+    //so its settings are still there when going back
+    stashed_title: Option<Title>,
+    pattern_dbs: PatternDbs,
+    ctrl_held: bool,
+    //the direction key being held and the time until it repeats
+    held_key: Option<(Key, Direction, f64)>
 }
 
 
@@ -28,84 +54,479 @@ impl App {
                 });
             },
             State::Title(title) => {
+                let layout = Layout::fit(args.width, args.height);
+                self.gl.draw(args.viewport(), |c, gl|{
+                    title.render(gl, &layout.transform(&c.transform), glyph, &layout.args(args));
+                });
+            },
+            State::Leaderboard(leaderboard) => {
+                let layout = Layout::fit(args.width, args.height);
                 self.gl.draw(args.viewport(), |c, gl|{
-                    title.render(gl, &c.transform, glyph, args);
+                    leaderboard.render(gl, &layout.transform(&c.transform), glyph, &layout.args(args));
                 });
             }
         }    
     }
 
     pub fn update(&mut self, args: &UpdateArgs) {
-        //nothing yet
-        match &mut self.state {
-            State::Game(grid) => {grid.update();},
+        let event = match &mut self.state {
+            State::Game(grid) => {
+                let mut event = grid.update(args);
+
+                if let Some((key, dir, mut timer)) = self.held_key {
+                    timer -= args.dt;
+                    while timer <= 0.0 {
+                        timer += KEY_REPEAT_RATE;
+                        //held keys wait for the last move to play out
+                        //instead of piling up behind it
+                        if grid.is_busy() {
+                            continue;
+                        }
+                        match grid.key_move(dir) {
+                            GameEvent::NoEvent => {},
+                            repeat_event => event = repeat_event
+                        }
+                    }
+                    self.held_key = Some((key, dir, timer));
+                }
+
+                event
+            },
             State::Title(title) => {
                 title.update();
+                GameEvent::NoEvent
+            },
+            State::Leaderboard(_) => GameEvent::NoEvent
+        };
+
+        self.handle_grid_event(event);
+    }
+
+    //a game that's over goes back to the title
+    fn handle_grid_event(&mut self, event: GameEvent) {
+        let new_state = match (&self.state, event) {
+            (State::Game(grid), GameEvent::Completed) => {
+                App::complete_game(grid, &mut self.scores, &mut self.daily)
+            },
+            (State::Game(grid), GameEvent::Failed) => {
+                App::fail_game(grid, &self.scores, &self.daily)
+            },
+            _ => return
+        };
+
+        self.held_key = None;
+        self.state = new_state;
+    }
+
+    //a new game is played the way it was set up on the title
+    fn start_game(mut grid: Grid, title: &Title, pattern_dbs: &PatternDbs) -> State {
+        grid.metric = title.metric;
+        grid.player_name = title.player_name.clone();
+        if let Some(slack) = title.move_limit {
+            let db = pattern_dbs.get(grid.x_cells, grid.y_cells);
+            if let Err(reason) = grid.set_move_limit(slack, db) {
+                App::report_error("Move Limit", &format!("Playing without a move limit: {}", reason));
             }
         }
+        App::resume_game(grid, title)
+    }
+
+    //a continued game keeps its own settings, only the
+    //controls and slide speed follow what's on the title
+    fn resume_game(mut grid: Grid, title: &Title) -> State {
+        grid.key_mode = title.key_mode;
+        grid.speed = title.speed;
+        State::Game(grid)
+    }
+
+
+    fn complete_game(grid: &Grid, scores: &mut ScoreBook, daily: &mut DailyBook) -> State {
+        let score = Score {
+            moves: grid.move_counter,
+            time_ms: grid.elapsed_ms(),
+            seed: grid.seed
+        };
+        println!("Congratz! Moves:{} Time:{} Seed:{}",
+                 score.moves,
+                 score::format_time(score.time_ms),
+                 score.seed);
+
+        let key = ScoreKey::new(&grid.settings());
+
+        if grid.assisted {
+            println!("Game was assisted, not counting it");
+        } else {
+            if score.beats_moves(&scores.best_moves(&key)) {
+                println!("New High Score: {}", score.moves);
+            }
+            if score.beats_time(&scores.best_time(&key)) {
+                println!("New Best Time: {}", score::format_time(score.time_ms));
+            }
+            scores.add(key, score, &grid.player_name);
+            if let Some(day) = grid.daily {
+                daily.add(day, &grid.player_name, grid.metric, score);
+            }
+        }
+
+        App::back_to_title(grid, scores, daily)
+    }
+
+    fn fail_game(grid: &Grid, scores: &ScoreBook, daily: &DailyBook) -> State {
+        println!("Out of moves! Moves:{} Seed:{}", grid.move_counter, grid.seed);
+        App::back_to_title(grid, scores, daily)
+    }
+
+    //back to the title with the same settings
+    fn back_to_title(grid: &Grid, scores: &ScoreBook, daily: &DailyBook) -> State {
+        grid.stop_solving();
+        let mut title = Title::new();
+        title.grid_w = grid.x_cells;
+        title.grid_h = grid.y_cells;
+        title.grid_img_path = grid.img_path.clone();
+        title.fit = grid.fit;
+        title.shuffle = grid.shuffle;
+        title.metric = grid.metric;
+        title.key_mode = grid.key_mode;
+        title.speed = grid.speed;
+        title.player_name = grid.player_name.clone();
+        title.move_limit = grid.move_limit;
+        //loopover boards have none, so B is left at its default
+        if grid.blank_count() > 0 {
+            title.blanks = grid.blank_count();
+        }
+        title.goal = grid.goal();
+        title.variant = grid.variant();
+        title.refresh_scores(scores);
+        title.refresh_daily(daily);
+        State::Title(title)
     }
 
 
     pub fn click(&mut self, raw_x: f32, raw_y: f32, w: u32, h: u32) {
         //let _state = self.state.clone();
+        if let State::Game(grid) = &mut self.state {
+            let (raw_x, raw_y) = (raw_x as f64, raw_y as f64);
+            let cell = grid.cell_at(raw_x, raw_y, w, h);
+            let event = match grid.on_board(cell) {
+                Some((cell_x, cell_y)) => {
+                    let offset = grid.offset_in_cell(raw_x, raw_y, w, h);
+                    grid.click(cell_x, cell_y, offset)
+                },
+                None => GameEvent::NoEvent
+            };
+            self.handle_grid_event(event);
+            return;
+        }
+
         let new_state = match &mut self.state {
-            State::Game(grid) => {
-                //this should be moved to the grid file
-                let cell_width = w as f32 / grid.x_cells as f32;
-                let cell_height = h as f32 / grid.y_cells as f32;
-                let cell_x = (raw_x as f32 / cell_width) as u32;
-                let cell_y = (raw_y as f32 / cell_height) as u32;
-                let event = grid.click(cell_x, cell_y);
-                //let event = grid.click(raw_x, raw_y, w, h);
+            State::Game(_) => None,
+            State::Title(title) => {
+                let (x, y) = Layout::fit(w, h).to_layout(raw_x as f64, raw_y as f64);
+                let event = title.click(x, y, LAYOUT_WIDTH, LAYOUT_HEIGHT);
                 match event {
-                    GameEvent::Completed => {
-                        println!("Congratz! Moves:{}", &grid.move_counter);
-                        let new_high_score = if grid.move_counter < self.high_score || 
-                                                self.high_score == 0 {
-                            grid.move_counter
-                        } else {
-                            self.high_score
+                    TitleEvent::PlayClick if title.blank_cells() >= title.grid_w * title.grid_h => {
+                        println!("There has to be at least one tile");
+                        None
+                    },
+                    TitleEvent::PlayClick => {
+                        let seed = match title.seed {
+                            Some(seed) => seed,
+                            None => board::random_seed()
                         };
-                        self.high_score = new_high_score;
-                        println!("New High Score: {}", &self.high_score);
-                        Some(State::Title(Title::new(new_high_score)))
-                    }
-                    GameEvent::NoEvent => {
+                        match Grid::new(&title.settings(), seed, w, h) {
+                            Ok(grid) => Some(App::start_game(grid, title, &self.pattern_dbs)),
+                            Err(e) => {
+                                App::report_error("Play", &format!("Couldn't start the game: {}", e));
+                                None
+                            }
+                        }
+                    },
+                    TitleEvent::DailyClick => {
+                        let day = daily::today();
+                        //everyone plays the same board, only the picture
+                        //and how moves are counted are up to the player
+                        let settings = GameSettings {
+                            x_cells: daily::DAILY_WIDTH,
+                            y_cells: daily::DAILY_HEIGHT,
+                            blanks: 1,
+                            goal: GoalLayout::RowMajor,
+                            variant: Variant::Slide,
+                            shuffle: daily::DAILY_SHUFFLE,
+                            ..title.settings()
+                        };
+                        match Grid::new(&settings, daily::seed_for(day), w, h) {
+                            Ok(mut grid) => {
+                                grid.daily = Some(day);
+                                Some(App::start_game(grid, title, &self.pattern_dbs))
+                            },
+                            Err(e) => {
+                                App::report_error("Daily", &format!("Couldn't start the daily board: {}", e));
+                                None
+                            }
+                        }
+                    },
+                    TitleEvent::ContinueClick => {
+                        //the save is used up once the game is picked back up,
+                        //it gets written again if the window is closed mid
+                        //game. one that can't be read is thrown away, but not
+                        //one that couldn't be opened or whose picture is
+                        //missing, that can come back
+                        let resumed = match SavedGame::load() {
+                            Ok(saved) => Grid::from_saved(saved, w, h).map_err(|e| {
+                                let broken = match e {
+                                    LoadError::Image(..) => false,
+                                    _ => true
+                                };
+                                (e.to_string(), broken)
+                            }),
+                            Err(e) => Err((e.to_string(), e.kind() == io::ErrorKind::InvalidData))
+                        };
+                        match resumed {
+                            Ok(grid) => {
+                                SavedGame::delete();
+                                Some(App::resume_game(grid, title))
+                            },
+                            Err((e, broken)) => {
+                                if broken {
+                                    SavedGame::delete();
+                                }
+                                App::report_error("Continue", &format!("Couldn't continue saved game: {}", e));
+                                title.refresh_continue();
+                                None
+                            }
+                        }
+                    },
+                    TitleEvent::LoadClick(path) => {
+                        let loaded = fs::read_to_string(&path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|text| Grid::from_text(&text,
+                                                             title.goal,
+                                                             title.variant,
+                                                             w,
+                                                             h,
+                                                             &title.grid_img_path,
+                                                             title.fit)
+                                      .map_err(|e| e.to_string()));
+                        match loaded {
+                            Ok(grid) => Some(App::start_game(grid, title, &self.pattern_dbs)),
+                            Err(e) => {
+                                App::report_error("Load Board", &format!("Couldn't load {}: {}", path, e));
+                                None
+                            }
+                        }
+                    },
+                    TitleEvent::LeaderboardClick => {
+                        Some(State::Leaderboard(
+                            Leaderboard::new(&self.scores, &title.score_key())
+                        ))
+                    },
+                    TitleEvent::NoEvent => {
+                        //the settings may have changed
+                        title.refresh_scores(&self.scores);
+                        title.refresh_daily(&self.daily);
                         None
                     }
                 }
             },
-            State::Title(title) => {
-                let event = title.click(raw_x as u32, raw_y as u32, w, h);
-                match event {
-                    TitleEvent::PlayClick => Some(State::Game(
-                            Grid::new(title.grid_w,
-                                      title.grid_h,
-                                      w,
-                                      h,
-                                      &title.grid_img_path)
-                    )),
-                    TitleEvent::NoEvent => None
+            State::Leaderboard(leaderboard) => {
+                let (x, y) = Layout::fit(w, h).to_layout(raw_x as f64, raw_y as f64);
+                match leaderboard.click(x, y, LAYOUT_WIDTH, LAYOUT_HEIGHT, &self.scores) {
+                    LeaderboardEvent::BackClick => {
+                        let mut title = match self.stashed_title.take() {
+                            Some(title) => title,
+                            None => Title::new()
+                        };
+                        title.refresh_scores(&self.scores);
+                        title.refresh_daily(&self.daily);
+                        Some(State::Title(title))
+                    },
+                    LeaderboardEvent::NoEvent => None
                 }
             }
         };
 
         if let Some(state) = new_state {
-            self.state = state;
+            self.held_key = None;
+            let old_state = mem::replace(&mut self.state, state);
+            if let (State::Title(title), State::Leaderboard(_)) = (old_state, &self.state) {
+                self.stashed_title = Some(title);
+            }
         }
 
     }
+    //letting go of the mouse finishes a loopover drag. the cell can be
+    //off the board, it only matters how far it is from the start
+    pub fn release(&mut self, raw_x: f32, raw_y: f32, w: u32, h: u32) {
+        let event = match &mut self.state {
+            State::Game(grid) => {
+                let (cell_x, cell_y) = grid.cell_at(raw_x as f64, raw_y as f64, w, h);
+                grid.release(cell_x, cell_y)
+            },
+            State::Title(_) | State::Leaderboard(_) => GameEvent::NoEvent
+        };
+
+        self.handle_grid_event(event);
+    }
+
+    //right clicking a tile turns it on boards where tiles turn
+    pub fn right_click(&mut self, raw_x: f32, raw_y: f32, w: u32, h: u32) {
+        let event = match &mut self.state {
+            State::Game(grid) => {
+                let cell = grid.cell_at(raw_x as f64, raw_y as f64, w, h);
+                match grid.on_board(cell) {
+                    Some((cell_x, cell_y)) => grid.rotate(cell_x, cell_y),
+                    None => GameEvent::NoEvent
+                }
+            },
+            State::Title(_) | State::Leaderboard(_) => GameEvent::NoEvent
+        };
+
+        self.handle_grid_event(event);
+    }
+
+    fn key_direction(key: Key) -> Option<Direction> {
+        match key {
+            Key::Up | Key::W => Some(Direction::Up),
+            Key::Down | Key::S => Some(Direction::Down),
+            Key::Left | Key::A => Some(Direction::Left),
+            Key::Right | Key::D => Some(Direction::Right),
+            _ => None
+        }
+    }
+
+    pub fn key_press(&mut self, key: Key) {
+        if key == Key::LCtrl || key == Key::RCtrl {
+            self.ctrl_held = true;
+        }
+
+        //the window repeats held keys itself, but we
+        //do our own repeating in update
+        if let Some((held, _, _)) = self.held_key {
+            if held == key {
+                return;
+            }
+        }
+
+        let event = match &mut self.state {
+            State::Game(grid) => {
+                match key {
+                    Key::Space => {
+                        let db = self.pattern_dbs.get(grid.x_cells, grid.y_cells);
+                        grid.auto_solve(db);
+                        GameEvent::NoEvent
+                    },
+                    Key::H => {
+                        let db = self.pattern_dbs.get(grid.x_cells, grid.y_cells);
+                        grid.hint(db);
+                        GameEvent::NoEvent
+                    },
+                    Key::C if self.ctrl_held => {
+                        App::export_board(grid);
+                        GameEvent::NoEvent
+                    },
+                    Key::R => grid.rotate_hovered(),
+                    Key::Z if self.ctrl_held => grid.undo(),
+                    Key::Y if self.ctrl_held => grid.redo(),
+                    _ => match App::key_direction(key) {
+                        Some(dir) if !self.ctrl_held => {
+                            self.held_key = Some((key, dir, KEY_REPEAT_DELAY));
+                            grid.key_move(dir)
+                        },
+                        _ => GameEvent::NoEvent
+                    }
+                }
+            },
+            State::Title(_) | State::Leaderboard(_) => GameEvent::NoEvent
+        };
+
+        self.handle_grid_event(event);
+    }
+
+    //print the position and offer to write it to a file
+    fn export_board(grid: &Grid) {
+        let text = grid.to_text();
+        print!("{}", text);
+
+        let path = match tinyfiledialogs::save_file_dialog("Save Board", "./board.txt") {
+            Some(path) => path,
+            None => return
+        };
+        let written = File::create(&path).and_then(|mut file| file.write_all(text.as_bytes()));
+        match written {
+            Ok(()) => println!("Board written to {}", path),
+            Err(e) => println!("Couldn't write {}: {}", path, e)
+        }
+    }
+
+    //when a game can't be played the way it was asked for, say why
+    fn report_error(title: &str, msg: &str) {
+        println!("{}", msg);
+        tinyfiledialogs::message_box_ok(title, msg, MessageBoxIcon::Error);
+    }
+
+    pub fn key_release(&mut self, key: Key) {
+        if key == Key::LCtrl || key == Key::RCtrl {
+            self.ctrl_held = false;
+        }
+
+        if let Some((held, _, _)) = self.held_key {
+            if held == key {
+                self.held_key = None;
+            }
+        }
+    }
+
+    //called as the window closes, a game in progress
+    //is kept so it can be continued from the title
+    pub fn exit(&mut self) {
+        if let State::Game(grid) = &self.state {
+            grid.stop_solving();
+            if grid.is_solved() {
+                return;
+            }
+            match grid.to_saved().save() {
+                Ok(()) => println!("Game saved"),
+                Err(e) => println!("Couldn't save game: {}", e)
+            }
+        }
+    }
+
     pub fn mouse_move(&mut self, raw_x: u32, raw_y: u32, w: u32, h: u32) {
+        let (x, y) = Layout::fit(w, h).to_layout(raw_x as f64, raw_y as f64);
         match &mut self.state {
             State::Game(grid) => {grid.mouse_move(raw_x, raw_y, w, h);},
-            State::Title(title) => {title.mouse_move(raw_x, raw_y, w, h);}
+            State::Title(title) => {title.mouse_move(x, y, LAYOUT_WIDTH, LAYOUT_HEIGHT);},
+            State::Leaderboard(leaderboard) => {
+                leaderboard.mouse_move(x, y, LAYOUT_WIDTH, LAYOUT_HEIGHT);
+            }
+        }
+    }
+
+    //the tiles are cut to fit the window, so they're cut again when it changes
+    pub fn resize(&mut self, w: u32, h: u32) {
+        if let State::Game(grid) = &mut self.state {
+            grid.resize(w, h);
         }
     }
     pub fn new(width: u32, height: u32, gl: GlGraphics) -> App {
+        let scores = ScoreBook::load();
+        let mut title = Title::new();
+        if let Some(name) = scores.last_name() {
+            title.player_name = name;
+        }
+        let daily = DailyBook::load();
+        title.refresh_scores(&scores);
+        title.refresh_daily(&daily);
+
         App {
             gl: gl,
-            state: State::Title(Title::new(0)),
-            high_score: 0
+            state: State::Title(title),
+            scores: scores,
+            daily: daily,
+            stashed_title: None,
+            pattern_dbs: PatternDbs::load_in_background(),
+            ctrl_held: false,
+            held_key: None
                 //grid: Grid::new(5,5, width, height, "./res/sample.jpg")
         }
     }
@@ -0,0 +1,1285 @@
+extern crate rand;
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use self::rand::{Rng, SeedableRng};
+use self::rand::prng::ChaChaRng;
+
+
+//same seed, same shuffle. ChaCha is used rather than StdRng since
+//StdRng is allowed to change between versions of rand
+pub fn seeded_rng(seed: u64) -> ChaChaRng {
+    //spread the seed over the whole key with splitmix64
+    let mut state = seed;
+    let mut key = [0u8; 32];
+    for chunk in key.chunks_mut(8) {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        for (i, byte) in chunk.iter_mut().enumerate() {
+            *byte = (z >> (i * 8)) as u8;
+        }
+    }
+    ChaChaRng::from_seed(key)
+}
+
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BoardError {
+    WrongSize { expected: usize, found: usize },
+    InvalidTile(u32),
+    DuplicateTile(u32),
+    BlankCount(usize),
+    Unsolvable
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardError::WrongSize { expected, found } =>
+                write!(f, "expected {} cells but found {}", expected, found),
+            BoardError::InvalidTile(tile) =>
+                write!(f, "tile {} doesn't fit on this board", tile),
+            BoardError::DuplicateTile(tile) =>
+                write!(f, "tile {} appears more than once", tile),
+            BoardError::BlankCount(count) =>
+                write!(f, "there can't be {} blanks on this board", count),
+            BoardError::Unsolvable =>
+                write!(f, "this arrangement can't be solved")
+        }
+    }
+}
+
+impl Error for BoardError {
+    fn description(&self) -> &str {
+        "invalid board"
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ShuffleMode {
+    //any solvable arrangement, all equally likely
+    Uniform,
+    //a short random walk back from the solved board
+    Scramble
+}
+
+impl ShuffleMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShuffleMode::Uniform => "Uniform",
+            ShuffleMode::Scramble => "Short Scramble"
+        }
+    }
+
+    //how it's written in the scores file
+    pub fn key(&self) -> &'static str {
+        match self {
+            ShuffleMode::Uniform => "uniform",
+            ShuffleMode::Scramble => "scramble"
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<ShuffleMode> {
+        match key {
+            "uniform" => Some(ShuffleMode::Uniform),
+            "scramble" => Some(ShuffleMode::Scramble),
+            _ => None
+        }
+    }
+
+    pub fn next(&self) -> ShuffleMode {
+        match self {
+            ShuffleMode::Uniform => ShuffleMode::Scramble,
+            ShuffleMode::Scramble => ShuffleMode::Uniform
+        }
+    }
+}
+
+
+//the order the tiles go in when the board is solved
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GoalLayout {
+    //row by row with the blank in the bottom right
+    RowMajor,
+    //row by row after the blank in the top left
+    BlankFirst,
+    //clockwise from the top left towards the middle
+    Spiral,
+    //row by row, turning back at the end of each row
+    Snake,
+    //column by column with the blank in the bottom right
+    ColumnMajor
+}
+
+impl GoalLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GoalLayout::RowMajor => "Rows",
+            GoalLayout::BlankFirst => "Blank First",
+            GoalLayout::Spiral => "Spiral",
+            GoalLayout::Snake => "Snake",
+            GoalLayout::ColumnMajor => "Columns"
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            GoalLayout::RowMajor => "rows",
+            GoalLayout::BlankFirst => "blank_first",
+            GoalLayout::Spiral => "spiral",
+            GoalLayout::Snake => "snake",
+            GoalLayout::ColumnMajor => "columns"
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<GoalLayout> {
+        match key {
+            "rows" => Some(GoalLayout::RowMajor),
+            "blank_first" => Some(GoalLayout::BlankFirst),
+            "spiral" => Some(GoalLayout::Spiral),
+            "snake" => Some(GoalLayout::Snake),
+            "columns" => Some(GoalLayout::ColumnMajor),
+            _ => None
+        }
+    }
+
+    pub fn next(&self) -> GoalLayout {
+        match self {
+            GoalLayout::RowMajor => GoalLayout::BlankFirst,
+            GoalLayout::BlankFirst => GoalLayout::Spiral,
+            GoalLayout::Spiral => GoalLayout::Snake,
+            GoalLayout::Snake => GoalLayout::ColumnMajor,
+            GoalLayout::ColumnMajor => GoalLayout::RowMajor
+        }
+    }
+
+    //every cell in the order it's filled
+    fn order(&self, width: u32, height: u32) -> Vec<(u32, u32)> {
+        match self {
+            GoalLayout::RowMajor | GoalLayout::BlankFirst => (0..width * height)
+                .map(|i| (i % width, i / width))
+                .collect(),
+            GoalLayout::Snake => (0..width * height)
+                .map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    if y % 2 == 0 { (x, y) } else { (width - 1 - x, y) }
+                })
+                .collect(),
+            GoalLayout::ColumnMajor => (0..width * height)
+                .map(|i| (i / height, i % height))
+                .collect(),
+            GoalLayout::Spiral => {
+                let mut order = Vec::with_capacity((width * height) as usize);
+                let (mut left, mut top) = (0i32, 0i32);
+                let (mut right, mut bottom) = (width as i32 - 1, height as i32 - 1);
+                while left <= right && top <= bottom {
+                    for x in left..=right {
+                        order.push((x, top));
+                    }
+                    for y in top + 1..=bottom {
+                        order.push((right, y));
+                    }
+                    if top < bottom {
+                        for x in (left..right).rev() {
+                            order.push((x, bottom));
+                        }
+                    }
+                    if left < right {
+                        for y in (top + 1..bottom).rev() {
+                            order.push((left, y));
+                        }
+                    }
+                    left += 1;
+                    top += 1;
+                    right -= 1;
+                    bottom -= 1;
+                }
+                order.into_iter().map(|(x, y)| (x as u32, y as u32)).collect()
+            }
+        }
+    }
+
+    //the solved arrangement, row major with None for the blanks
+    pub fn goal_cells(&self, width: u32, height: u32, blanks: u32) -> Vec<Option<u32>> {
+        let tiles = width * height - blanks;
+        let mut cells = vec![None; (width * height) as usize];
+
+        for (i, (x, y)) in self.order(width, height).into_iter().enumerate() {
+            let i = i as u32;
+            let tile = match self {
+                GoalLayout::BlankFirst => if i >= blanks { Some(i - blanks) } else { None },
+                _ => if i < tiles { Some(i) } else { None }
+            };
+            cells[Board::get_index(x, y, width) as usize] = tile;
+        }
+
+        cells
+    }
+}
+
+
+//the rules the board is played with
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Variant {
+    Slide,
+    //the edges join up, so tiles can slide off one side onto the other
+    Wrap,
+    //tiles are turned as well as moved, and have to end up upright
+    Rotate,
+    //no blank, whole rows and columns are turned round instead
+    Loopover
+}
+
+impl Variant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Slide => "Slide",
+            Variant::Wrap => "Wrap",
+            Variant::Rotate => "Rotate",
+            Variant::Loopover => "Loopover"
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Variant::Slide => "slide",
+            Variant::Wrap => "wrap",
+            Variant::Rotate => "rotate",
+            Variant::Loopover => "loopover"
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Variant> {
+        match key {
+            "slide" => Some(Variant::Slide),
+            "wrap" => Some(Variant::Wrap),
+            "rotate" => Some(Variant::Rotate),
+            "loopover" => Some(Variant::Loopover),
+            _ => None
+        }
+    }
+
+    pub fn next(&self) -> Variant {
+        match self {
+            Variant::Slide => Variant::Wrap,
+            Variant::Wrap => Variant::Rotate,
+            Variant::Rotate => Variant::Loopover,
+            Variant::Loopover => Variant::Slide
+        }
+    }
+}
+
+
+//a row or column of a loopover board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Line {
+    Row(u32),
+    Column(u32)
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right
+}
+
+impl Direction {
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0)
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left
+        }
+    }
+
+    pub fn all() -> [Direction; 4] {
+        [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+    }
+
+    //all four, the ones pointing most along (dx, dy) first
+    pub fn towards(dx: f64, dy: f64) -> [Direction; 4] {
+        let along = |dir: &Direction| {
+            let (x, y) = dir.delta();
+            x as f64 * dx + y as f64 * dy
+        };
+        let mut dirs = Direction::all();
+        dirs.sort_by(|a, b| along(b).partial_cmp(&along(a)).unwrap_or(Ordering::Equal));
+        dirs
+    }
+}
+
+
+//the puzzle state on its own, without any textures or
+//rendering attached, so it can be driven without a window.
+//there can be more than one blank, they're all None
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Board {
+    pub width: u32,
+    pub height: u32,
+    cells: Vec<Option<u32>>,
+    //quarter turns clockwise of the tile in each cell, 0 is upright
+    turns: Vec<u8>,
+    goal: GoalLayout,
+    variant: Variant
+}
+
+impl Board {
+
+    pub fn get_index(x: u32, y: u32, w: u32) -> u32 {
+        x + y * w
+    }
+
+
+    pub fn get(&self, x: u32, y: u32) -> Option<u32> {
+        match self.cells.get(Board::get_index(x, y, self.width) as usize) {
+            Some(content) => *content,
+            None => None
+        }
+    }
+
+    fn is_blank(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height &&
+            self.cells[Board::get_index(x, y, self.width) as usize].is_none()
+    }
+
+    pub fn cells(&self) -> &[Option<u32>] {
+        &self.cells
+    }
+
+    //every blank in row major order
+    pub fn blanks(&self) -> Vec<(u32, u32)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_none())
+            .map(|(i, _)| (i as u32 % width, i as u32 / width))
+            .collect()
+    }
+
+    pub fn blank_count(&self) -> usize {
+        self.cells.iter().filter(|c| c.is_none()).count()
+    }
+
+    pub fn goal(&self) -> GoalLayout {
+        self.goal
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn wraps(&self) -> bool {
+        self.variant == Variant::Wrap
+    }
+
+    pub fn rotates(&self) -> bool {
+        self.variant == Variant::Rotate
+    }
+
+    pub fn loops(&self) -> bool {
+        self.variant == Variant::Loopover
+    }
+
+    pub fn turns(&self) -> &[u8] {
+        &self.turns
+    }
+
+    pub fn turn(&self, x: u32, y: u32) -> u8 {
+        match self.turns.get(Board::get_index(x, y, self.width) as usize) {
+            Some(turn) => *turn,
+            None => 0
+        }
+    }
+
+    //turn the tile at (x, y) clockwise, returns false if
+    //there's no tile there or tiles don't turn on this board
+    pub fn rotate(&mut self, x: u32, y: u32, quarter_turns: u8) -> bool {
+        if !self.rotates() || x >= self.width || y >= self.height || self.is_blank(x, y) {
+            return false;
+        }
+
+        let i = Board::get_index(x, y, self.width) as usize;
+        self.turns[i] = (self.turns[i] + quarter_turns) % 4;
+        true
+    }
+
+    //the turns for every cell in row major order, blanks are never turned
+    pub fn set_turns(&mut self, turns: Vec<u8>) -> Result<(), BoardError> {
+        if turns.len() != self.cells.len() {
+            return Err(BoardError::WrongSize {
+                expected: self.cells.len(),
+                found: turns.len()
+            });
+        }
+
+        self.turns = turns
+            .iter()
+            .zip(self.cells.iter())
+            .map(|(turn, content)| if content.is_some() { turn % 4 } else { 0 })
+            .collect();
+        Ok(())
+    }
+
+    //the cell next to pos going in dir, or None off the edge.
+    //on a wrapping board the far side is next to the edge
+    pub fn step(&self, pos: (u32, u32), dir: Direction) -> Option<(u32, u32)> {
+        let (dx, dy) = dir.delta();
+        let (x, y) = (pos.0 as i32 + dx, pos.1 as i32 + dy);
+        let (w, h) = (self.width as i32, self.height as i32);
+
+        if self.wraps() {
+            let next = (((x + w) % w) as u32, ((y + h) % h) as u32);
+            //a single row can't wrap onto itself
+            if next == pos { None } else { Some(next) }
+        } else if x < 0 || y < 0 || x >= w || y >= h {
+            None
+        } else {
+            Some((x as u32, y as u32))
+        }
+    }
+
+    //same as step, but going steps cells
+    pub fn walk(&self, pos: (u32, u32), dir: Direction, steps: u32) -> Option<(u32, u32)> {
+        let mut pos = pos;
+        for _ in 0..steps {
+            pos = self.step(pos, dir)?;
+        }
+        Some(pos)
+    }
+
+    pub fn goal_cells(&self) -> Vec<Option<u32>> {
+        self.goal.goal_cells(self.width, self.height, self.blank_count() as u32)
+    }
+
+    //the first blank, which is the only one on a normal board
+    pub fn empty(&self) -> (u32, u32) {
+        let i = self.cells.iter().position(|c| c.is_none()).unwrap() as u32;
+        (i % self.width, i / self.width)
+    }
+
+
+    //every cell that can slide into a blank, in the order
+    //up, down, left, right from each blank in turn. the game goes
+    //through slide_blank, these are for walking boards in the tests
+    #[cfg(test)]
+    pub fn legal_moves(&self) -> Vec<(u32, u32)> {
+        let mut moves: Vec<(u32, u32)> = Vec::new();
+
+        for blank in self.blanks() {
+            for &dir in Direction::all().iter() {
+                if let Some((x, y)) = self.step(blank, dir) {
+                    if !self.is_blank(x, y) && !moves.contains(&(x, y)) {
+                        moves.push((x, y));
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+
+    //the nearest blank in the same row or column as the tile at (x, y)
+    //with only tiles in between, ties go to whichever way comes first in
+    //order. gives the blank, the way it has to go to reach the tile and
+    //how far that is
+    pub fn line_blank(&self,
+                      x: u32,
+                      y: u32,
+                      order: [Direction; 4]) -> Option<((u32, u32), Direction, u32)> {
+        if x >= self.width || y >= self.height || self.is_blank(x, y) {
+            return None;
+        }
+
+        let mut nearest: Option<((u32, u32), Direction, u32)> = None;
+        for &dir in order.iter() {
+            let mut pos = (x, y);
+            let mut distance = 0;
+            while let Some(next) = self.step(pos, dir) {
+                //all the way round without finding one
+                if next == (x, y) {
+                    break;
+                }
+                pos = next;
+                distance += 1;
+
+                if self.is_blank(pos.0, pos.1) {
+                    let closer = match nearest {
+                        Some((_, _, best)) => distance < best,
+                        None => true
+                    };
+                    if closer {
+                        nearest = Some((pos, dir.opposite(), distance));
+                    }
+                    break;
+                }
+            }
+        }
+
+        nearest
+    }
+
+
+    //slide the tile at (x, y) into a blank next to it,
+    //returns false and leaves the board alone if there isn't one
+    #[cfg(test)]
+    pub fn apply_move(&mut self, x: u32, y: u32) -> bool {
+        match self.line_blank(x, y, Direction::all()) {
+            Some((blank, dir, 1)) => self.slide_blank(blank, dir, 1) == 1,
+            _ => false
+        }
+    }
+
+
+    //move the blank at blank steps cells in dir, sliding each tile on
+    //the way back one step. returns how many tiles moved, which is 0 if
+    //it isn't a blank or there's another blank in the way
+    pub fn slide_blank(&mut self, blank: (u32, u32), dir: Direction, steps: u32) -> u32 {
+        if blank.0 >= self.width || blank.1 >= self.height || !self.is_blank(blank.0, blank.1) {
+            return 0;
+        }
+
+        //check the whole line first so nothing moves if it's blocked
+        let mut path = Vec::with_capacity(steps as usize);
+        let mut pos = blank;
+        for _ in 0..steps {
+            pos = match self.step(pos, dir) {
+                Some(next) if next != blank && !self.is_blank(next.0, next.1) => next,
+                _ => return 0
+            };
+            path.push(pos);
+        }
+
+        let mut from = Board::get_index(blank.0, blank.1, self.width) as usize;
+        for (x, y) in path {
+            let to = Board::get_index(x, y, self.width) as usize;
+            self.cells.swap(from, to);
+            self.turns.swap(from, to);
+            from = to;
+        }
+
+        steps
+    }
+
+
+    //turn a row right or a column down by amount cells, negative
+    //goes the other way. returns how many single steps that was,
+    //which is 0 if this isn't a loopover board
+    pub fn shift(&mut self, line: Line, amount: i32) -> u32 {
+        if !self.loops() {
+            return 0;
+        }
+
+        let width = self.width;
+        let indices: Vec<usize> = match line {
+            Line::Row(y) if y < self.height =>
+                (0..width).map(|x| Board::get_index(x, y, width) as usize).collect(),
+            Line::Column(x) if x < width =>
+                (0..self.height).map(|y| Board::get_index(x, y, width) as usize).collect(),
+            _ => return 0
+        };
+
+        let len = indices.len() as i32;
+        let steps = amount.abs() % len;
+        if steps == 0 {
+            return 0;
+        }
+
+        let cells: Vec<Option<u32>> = indices.iter().map(|&i| self.cells[i]).collect();
+        let turns: Vec<u8> = indices.iter().map(|&i| self.turns[i]).collect();
+        let offset = ((amount % len) + len) as usize;
+        for from in 0..indices.len() {
+            let to = indices[(from + offset) % indices.len()];
+            self.cells[to] = cells[from];
+            self.turns[to] = turns[from];
+        }
+
+        steps as u32
+    }
+
+    //every tile in place, and upright
+    pub fn is_solved(&self) -> bool {
+        self.cells == self.goal_cells() && self.turns.iter().all(|&turn| turn == 0)
+    }
+
+
+    //each slide swaps the blank with a tile, so the permutation from here
+    //to the goal has to be even exactly when the blank is an even number
+    //of steps from where it ends up. a second blank lets any two tiles
+    //trade places, so with more than one everything can be solved
+    pub fn is_solvable(&self) -> bool {
+        let goal = self.goal_cells();
+
+        //a single row or column can't reorder its tiles at all,
+        //though if it wraps they can all go round together
+        if self.width == 1 || self.height == 1 {
+            let tiles: Vec<u32> = self.cells.iter().filter_map(|c| *c).collect();
+            let goal_tiles: Vec<u32> = goal.iter().filter_map(|c| *c).collect();
+            if self.wraps() || self.loops() {
+                return (0..tiles.len().max(1)).any(|shift| {
+                    tiles[shift..].iter().chain(tiles[..shift].iter()).eq(goal_tiles.iter())
+                });
+            }
+            return tiles == goal_tiles;
+        }
+
+        if self.blank_count() > 1 {
+            return true;
+        }
+
+        //turning a line of even length is an odd permutation, so
+        //only when both sides are odd does the parity have to match
+        let odd_sides = self.width % 2 == 1 && self.height % 2 == 1;
+        if self.loops() && !odd_sides {
+            return true;
+        }
+
+        //wrapping round an odd side takes the blank an even number
+        //of cells away in one move, which flips the parity
+        if self.wraps() && (self.width % 2 == 1 || self.height % 2 == 1) {
+            return true;
+        }
+
+        //where everything on the board belongs, the blank being one past the tiles
+        let blank = self.cells.len() as u32 - 1;
+        let mut goal_index = vec![0; self.cells.len()];
+        for (i, content) in goal.iter().enumerate() {
+            goal_index[content.unwrap_or(blank) as usize] = i;
+        }
+        let targets: Vec<usize> = self.cells
+            .iter()
+            .map(|c| goal_index[c.unwrap_or(blank) as usize])
+            .collect();
+
+        //even permutations have an even number of even length cycles
+        let mut visited = vec![false; targets.len()];
+        let mut swaps = 0;
+        for start in 0..targets.len() {
+            let mut i = start;
+            let mut length = 0;
+            while !visited[i] {
+                visited[i] = true;
+                i = targets[i];
+                length += 1;
+            }
+            if length > 0 {
+                swaps += length - 1;
+            }
+        }
+
+        if self.loops() {
+            return swaps % 2 == 0;
+        }
+
+        let (ex, ey) = self.empty();
+        let goal_blank = goal_index[blank as usize] as u32;
+        let (gx, gy) = (goal_blank % self.width, goal_blank / self.width);
+        let distance = (ex as i32 - gx as i32).abs() + (ey as i32 - gy as i32).abs();
+
+        swaps % 2 == distance as usize % 2
+    }
+
+
+    //generate all neighbouring states,
+    //and go to one of the neighbouring states
+    //at random. each blank and way it can go is its own move, so
+    //a tile between two blanks can go into either of them
+    pub fn random_step<R: Rng>(&mut self, rng: &mut R) {
+        let mut possible_moves = Vec::new();
+        for blank in self.blanks() {
+            for &dir in Direction::all().iter() {
+                if let Some((x, y)) = self.step(blank, dir) {
+                    if !self.is_blank(x, y) {
+                        possible_moves.push((blank, dir));
+                    }
+                }
+            }
+        }
+
+        if let Some(&(blank, dir)) = rng.choose(&possible_moves) {
+            self.slide_blank(blank, dir, 1);
+        }
+    }
+
+    //begin at end state, do a random graph transversal
+    //with the specified depth
+    pub fn randomize<R: Rng>(&mut self, depth: u32, rng: &mut R) {
+        for _ in 0..depth {
+            self.random_step(rng);
+        }
+    }
+
+
+    //pick uniformly from every solvable arrangement. with one blank a
+    //random permutation is unsolvable half the time, and swapping the
+    //first two tiles pairs each of those up with exactly one solvable one
+    pub fn shuffle<R: Rng>(&mut self, rng: &mut R) {
+        let tile_count = self.cells.len() - self.blank_count();
+        loop {
+            if self.width == 1 || self.height == 1 {
+                //only the blanks can move in a single line
+                let mut blank: Vec<bool> = (0..self.cells.len())
+                    .map(|i| i >= tile_count)
+                    .collect();
+                rng.shuffle(&mut blank);
+                let mut tiles = 0..;
+                for i in 0..self.cells.len() {
+                    self.cells[i] = if blank[i] { None } else { tiles.next() };
+                }
+            } else {
+                rng.shuffle(&mut self.cells);
+            }
+
+            if !self.is_solvable() {
+                let tiles: Vec<usize> = (0..self.cells.len())
+                    .filter(|&i| self.cells[i].is_some())
+                    .take(2)
+                    .collect();
+                self.cells.swap(tiles[0], tiles[1]);
+            }
+
+            //don't hand out a board that is already done
+            if !self.is_solved() || tile_count == 0 {
+                break;
+            }
+        }
+    }
+
+    //with no blank, loopover boards are shuffled with their own moves
+    fn shuffle_loopover<R: Rng>(&mut self, mode: ShuffleMode, rng: &mut R) {
+        let size = self.cells.len();
+        let goal = self.goal_cells();
+
+        loop {
+            match mode {
+                ShuffleMode::Uniform if self.width == 1 || self.height == 1 => {
+                    //a single line can only go round
+                    let offset = rng.gen_range(0, size);
+                    for i in 0..size {
+                        self.cells[(i + offset) % size] = goal[i];
+                    }
+                },
+                ShuffleMode::Uniform => {
+                    rng.shuffle(&mut self.cells);
+                    if !self.is_solvable() {
+                        self.cells.swap(0, 1);
+                    }
+                },
+                ShuffleMode::Scramble => {
+                    for _ in 0..size * 10 {
+                        let line = if rng.gen() {
+                            Line::Row(rng.gen_range(0, self.height))
+                        } else {
+                            Line::Column(rng.gen_range(0, self.width))
+                        };
+                        self.shift(line, if rng.gen() { 1 } else { -1 });
+                    }
+                }
+            }
+
+            //don't hand out a board that is already done
+            if !self.is_solved() || size < 2 {
+                break;
+            }
+        }
+    }
+
+    pub fn shuffle_with<R: Rng>(&mut self, mode: ShuffleMode, rng: &mut R) {
+        if self.loops() {
+            self.shuffle_loopover(mode, rng);
+            return;
+        }
+
+        match mode {
+            ShuffleMode::Uniform => self.shuffle(rng),
+            ShuffleMode::Scramble => {
+                let depth = self.width * self.height * 10;
+                self.randomize(depth, rng);
+            }
+        }
+
+        //any turn can be undone, so they're all picked at random
+        if self.rotates() {
+            for i in 0..self.cells.len() {
+                self.turns[i] = if self.cells[i].is_some() { rng.gen_range(0, 4) } else { 0 };
+            }
+        }
+    }
+
+
+    //build a board from an explicit arrangement, in row major order
+    //with None for the blanks. with k blanks tiles are numbered
+    //0..width*height-k, in the order given by the goal layout
+    pub fn from_cells_with_goal(width: u32,
+                                height: u32,
+                                cells: Vec<Option<u32>>,
+                                goal: GoalLayout,
+                                variant: Variant) -> Result<Board, BoardError> {
+        let size = (width * height) as usize;
+        if cells.len() != size {
+            return Err(BoardError::WrongSize {
+                expected: size,
+                found: cells.len()
+            });
+        }
+
+        //loopover boards are the only ones without a blank
+        let blanks = cells.iter().filter(|c| c.is_none()).count();
+        let no_blank = variant == Variant::Loopover;
+        if (blanks == 0) != no_blank || blanks == size {
+            return Err(BoardError::BlankCount(blanks));
+        }
+
+        let tiles = size - blanks;
+        let mut seen = vec![false; tiles];
+        for tile in cells.iter().filter_map(|c| *c) {
+            if tile as usize >= tiles {
+                return Err(BoardError::InvalidTile(tile));
+            }
+            if seen[tile as usize] {
+                return Err(BoardError::DuplicateTile(tile));
+            }
+            seen[tile as usize] = true;
+        }
+
+        let board = Board {
+            width: width,
+            height: height,
+            turns: vec![0; cells.len()],
+            cells: cells,
+            goal: goal,
+            variant: variant
+        };
+
+        if !board.is_solvable() {
+            return Err(BoardError::Unsolvable);
+        }
+
+        Ok(board)
+    }
+
+
+    //the usual board, for the tests
+    #[cfg(test)]
+    pub fn new(width: u32, height: u32) -> Board {
+        Board::with_goal(width, height, 1, GoalLayout::RowMajor, Variant::Slide)
+    }
+
+    //a solved board
+    pub fn with_goal(width: u32,
+                     height: u32,
+                     blanks: u32,
+                     goal: GoalLayout,
+                     variant: Variant) -> Board {
+        Board {
+            width: width,
+            height: height,
+            cells: goal.goal_cells(width, height, blanks),
+            turns: vec![0; (width * height) as usize],
+            goal: goal,
+            variant: variant
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashSet, VecDeque};
+
+    //every arrangement the goal can be slid into
+    fn reachable(goal: &Board) -> HashSet<Board> {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(goal.clone());
+        queue.push_back(goal.clone());
+
+        while let Some(board) = queue.pop_front() {
+            for (x, y) in board.legal_moves() {
+                let mut next = board.clone();
+                next.apply_move(x, y);
+                if seen.insert(next.clone()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        seen
+    }
+
+    fn permutations(items: &[Option<u32>]) -> Vec<Vec<Option<u32>>> {
+        if items.is_empty() {
+            return vec![Vec::new()];
+        }
+        let mut all = Vec::new();
+        for i in 0..items.len() {
+            let mut rest = items.to_vec();
+            let first = rest.remove(i);
+            for mut tail in permutations(&rest) {
+                tail.insert(0, first);
+                all.push(tail);
+            }
+        }
+        all
+    }
+
+    #[test]
+    fn new_board_is_solved() {
+        let board = Board::new(3, 3);
+        assert!(board.is_solved());
+        assert_eq!(board.empty(), (2, 2));
+        assert_eq!(board.get(0, 0), Some(0));
+        assert_eq!(board.get(1, 2), Some(7));
+    }
+
+    #[test]
+    fn apply_move_slides_a_tile_into_the_blank() {
+        let mut board = Board::new(3, 3);
+        assert!(board.apply_move(1, 2));
+        assert_eq!(board.get(2, 2), Some(7));
+        assert_eq!(board.get(1, 2), None);
+        assert!(!board.is_solved());
+
+        //and back again
+        assert!(board.apply_move(2, 2));
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn apply_move_leaves_the_board_alone_otherwise() {
+        let mut board = Board::new(3, 3);
+        //not next to the blank, the blank itself, off the board
+        assert!(!board.apply_move(0, 0));
+        assert!(!board.apply_move(0, 2));
+        assert!(!board.apply_move(2, 2));
+        assert!(!board.apply_move(3, 0));
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn legal_moves_are_the_tiles_next_to_the_blank() {
+        let board = Board::new(3, 3);
+        assert_eq!(board.legal_moves(), vec![(2, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn random_walk_can_be_walked_back() {
+        let mut board = Board::new(4, 4);
+        let mut rng = seeded_rng(7);
+        let mut blanks = vec![board.empty()];
+        for _ in 0..50 {
+            board.random_step(&mut rng);
+            blanks.push(board.empty());
+        }
+
+        //sliding the tile where the blank was last puts it back
+        blanks.pop();
+        while let Some((x, y)) = blanks.pop() {
+            assert!(board.apply_move(x, y));
+        }
+        assert!(board.is_solved());
+    }
+
+    //odd and even widths, with the blank's row mattering on the even ones
+    #[test]
+    fn solvable_exactly_when_reachable() {
+        for &(w, h) in &[(3, 2), (2, 3), (4, 2), (2, 4)] {
+            let goal = Board::new(w, h);
+            let reachable = reachable(&goal);
+            for cells in permutations(goal.cells()) {
+                let board = Board {
+                    cells: cells,
+                    ..goal.clone()
+                };
+                assert_eq!(board.is_solvable(), reachable.contains(&board), "{:?}", board);
+            }
+        }
+    }
+
+    #[test]
+    fn swapping_two_tiles_is_unsolvable() {
+        for &(w, h) in &[(3, 3), (4, 4), (5, 5)] {
+            let mut cells = Board::new(w, h).cells().to_vec();
+            cells.swap(0, 1);
+            assert_eq!(Board::from_cells_with_goal(w, h, cells, GoalLayout::RowMajor, Variant::Slide),
+                       Err(BoardError::Unsolvable));
+        }
+    }
+
+    #[test]
+    fn shuffled_boards_are_solvable() {
+        let mut rng = seeded_rng(11);
+        for &(w, h) in &[(3, 3), (4, 4), (5, 4), (4, 5)] {
+            for &mode in &[ShuffleMode::Uniform, ShuffleMode::Scramble] {
+                let mut board = Board::new(w, h);
+                board.shuffle_with(mode, &mut rng);
+                assert!(board.is_solvable());
+                assert!(!board.is_solved());
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_shuffle() {
+        for &mode in &[ShuffleMode::Uniform, ShuffleMode::Scramble] {
+            let shuffled = |seed: u64| {
+                let mut board = Board::new(4, 4);
+                board.shuffle_with(mode, &mut seeded_rng(seed));
+                board.cells().to_vec()
+            };
+            assert_eq!(shuffled(42), shuffled(42));
+            assert_ne!(shuffled(42), shuffled(43));
+            assert_ne!(shuffled(0), shuffled(1));
+        }
+    }
+
+    #[test]
+    fn from_cells_rejects_bad_arrangements() {
+        let make = |cells: Vec<Option<u32>>| {
+            Board::from_cells_with_goal(2, 2, cells, GoalLayout::RowMajor, Variant::Slide)
+        };
+        assert_eq!(make(vec![Some(0), Some(1), None]),
+                   Err(BoardError::WrongSize { expected: 4, found: 3 }));
+        assert_eq!(make(vec![Some(0), Some(0), Some(1), None]), Err(BoardError::DuplicateTile(0)));
+        assert_eq!(make(vec![Some(0), Some(1), Some(3), None]), Err(BoardError::InvalidTile(3)));
+        assert_eq!(make(vec![Some(0), Some(1), Some(2), Some(3)]), Err(BoardError::BlankCount(0)));
+        assert!(make(vec![Some(0), Some(1), None, Some(2)]).is_ok());
+    }
+
+    #[test]
+    fn towards_puts_the_nearest_way_first() {
+        assert_eq!(Direction::towards(0.0, 0.0), Direction::all());
+        assert_eq!(Direction::towards(0.1, -0.4)[0], Direction::Up);
+        assert_eq!(Direction::towards(-0.3, 0.2),
+                   [Direction::Left, Direction::Down, Direction::Up, Direction::Right]);
+    }
+
+    #[test]
+    fn ties_between_blanks_go_the_way_asked() {
+        let cells = vec![None, Some(0), None,
+                         Some(1), Some(2), Some(3),
+                         Some(4), Some(5), Some(6)];
+        let board = Board::from_cells_with_goal(3, 3, cells, GoalLayout::RowMajor, Variant::Slide).unwrap();
+
+        assert_eq!(board.line_blank(1, 0, Direction::towards(-0.3, 0.1)),
+                   Some(((0, 0), Direction::Right, 1)));
+        assert_eq!(board.line_blank(1, 0, Direction::towards(0.3, 0.1)),
+                   Some(((2, 0), Direction::Left, 1)));
+        //a nearer blank still wins over the way asked
+        assert_eq!(board.line_blank(0, 2, Direction::towards(0.4, 0.0)),
+                   Some(((0, 0), Direction::Down, 2)));
+    }
+
+    #[test]
+    fn random_steps_use_every_blank() {
+        //the tile in the middle of the top row can go either way
+        let cells = vec![None, Some(0), None,
+                         Some(1), Some(2), Some(3)];
+        let board = Board::from_cells_with_goal(3, 2, cells, GoalLayout::RowMajor, Variant::Slide).unwrap();
+
+        let mut outcomes = HashSet::new();
+        let mut rng = seeded_rng(5);
+        for _ in 0..200 {
+            let mut next = board.clone();
+            next.random_step(&mut rng);
+            assert_ne!(next, board);
+            outcomes.insert(next);
+        }
+        //into the left blank, the right one, or a tile up from below
+        assert_eq!(outcomes.len(), 4);
+    }
+
+    fn rotate_board() -> Board {
+        Board::with_goal(3, 3, 1, GoalLayout::RowMajor, Variant::Rotate)
+    }
+
+    #[test]
+    fn four_turns_put_a_tile_back() {
+        let mut board = rotate_board();
+        for turn in 1..4 {
+            assert!(board.rotate(1, 1, 1));
+            assert_eq!(board.turn(1, 1), turn);
+            assert!(!board.is_solved());
+        }
+        assert!(board.rotate(1, 1, 1));
+        assert_eq!(board.turn(1, 1), 0);
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn one_turned_tile_is_not_solved() {
+        let mut board = rotate_board();
+        assert!(board.rotate(0, 0, 2));
+        assert_eq!(board.cells(), &board.goal_cells()[..]);
+        assert!(!board.is_solved());
+    }
+
+    #[test]
+    fn only_tiles_on_rotate_boards_turn() {
+        let mut board = rotate_board();
+        assert!(!board.rotate(2, 2, 1));
+        assert!(!board.rotate(3, 0, 1));
+        assert!(board.is_solved());
+
+        let mut board = Board::new(3, 3);
+        assert!(!board.rotate(0, 0, 1));
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn set_turns_checks_the_size_and_leaves_blanks_upright() {
+        let mut board = rotate_board();
+        assert_eq!(board.set_turns(vec![1; 8]), Err(BoardError::WrongSize { expected: 9, found: 8 }));
+        board.set_turns(vec![5; 9]).unwrap();
+        assert_eq!(board.turns(), &[1, 1, 1, 1, 1, 1, 1, 1, 0]);
+        board.set_turns(vec![0; 9]).unwrap();
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn turns_move_with_their_tile() {
+        let mut board = rotate_board();
+        board.rotate(2, 1, 3);
+        assert!(board.apply_move(2, 1));
+        assert_eq!(board.turn(2, 2), 3);
+        assert_eq!(board.turn(2, 1), 0);
+    }
+
+    fn loopover_board(width: u32, height: u32) -> Board {
+        Board::with_goal(width, height, 0, GoalLayout::RowMajor, Variant::Loopover)
+    }
+
+    fn some(cells: &[u32]) -> Vec<Option<u32>> {
+        cells.iter().map(|&c| Some(c)).collect()
+    }
+
+    //every arrangement a loopover goal can be turned into
+    fn reachable_by_shifts(goal: &Board) -> HashSet<Board> {
+        let mut lines = Vec::new();
+        for y in 0..goal.height {
+            lines.push(Line::Row(y));
+        }
+        for x in 0..goal.width {
+            lines.push(Line::Column(x));
+        }
+
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(goal.clone());
+        queue.push_back(goal.clone());
+        while let Some(board) = queue.pop_front() {
+            for &line in &lines {
+                let mut next = board.clone();
+                next.shift(line, 1);
+                if seen.insert(next.clone()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        seen
+    }
+
+    #[test]
+    fn shifts_go_round() {
+        let mut board = loopover_board(3, 3);
+        assert_eq!(board.shift(Line::Row(0), 1), 1);
+        assert_eq!(board.cells(), &some(&[2, 0, 1, 3, 4, 5, 6, 7, 8])[..]);
+        assert_eq!(board.shift(Line::Row(0), -1), 1);
+        assert!(board.is_solved());
+
+        assert_eq!(board.shift(Line::Column(1), 1), 1);
+        assert_eq!(board.cells(), &some(&[0, 7, 2, 3, 1, 5, 6, 4, 8])[..]);
+        assert_eq!(board.shift(Line::Column(1), -4), 1);
+        assert!(board.is_solved());
+
+        assert_eq!(board.shift(Line::Row(2), -2), 2);
+        assert_eq!(board.cells(), &some(&[0, 1, 2, 3, 4, 5, 8, 6, 7])[..]);
+    }
+
+    #[test]
+    fn shifts_that_go_nowhere_count_for_nothing() {
+        let mut board = loopover_board(3, 2);
+        assert_eq!(board.shift(Line::Row(0), 3), 0);
+        assert_eq!(board.shift(Line::Column(0), -2), 0);
+        assert_eq!(board.shift(Line::Row(2), 1), 0);
+        assert_eq!(board.shift(Line::Column(3), 1), 0);
+        assert!(board.is_solved());
+
+        let mut board = Board::new(3, 3);
+        assert_eq!(board.shift(Line::Row(0), 1), 0);
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn loopover_with_an_even_side_reaches_everything() {
+        for &(w, h) in &[(2, 2), (3, 2), (2, 3)] {
+            let reachable = reachable_by_shifts(&loopover_board(w, h));
+            let tiles: Vec<Option<u32>> = (0..w * h).map(Some).collect();
+            let all = permutations(&tiles);
+            assert_eq!(reachable.len(), all.len());
+            for cells in all {
+                let board = Board::from_cells_with_goal(w, h, cells, GoalLayout::RowMajor, Variant::Loopover);
+                assert!(board.map(|board| reachable.contains(&board)).unwrap_or(false));
+            }
+        }
+    }
+
+    #[test]
+    fn odd_loopover_boards_keep_their_parity() {
+        let mut board = loopover_board(3, 3);
+        let mut cells = board.cells().to_vec();
+        cells.swap(0, 1);
+        assert_eq!(Board::from_cells_with_goal(3, 3, cells, GoalLayout::RowMajor, Variant::Loopover),
+                   Err(BoardError::Unsolvable));
+
+        //turning a line of three is an even permutation
+        let mut rng = seeded_rng(4);
+        for _ in 0..50 {
+            let line = if rng.gen() {
+                Line::Row(rng.gen_range(0, 3))
+            } else {
+                Line::Column(rng.gen_range(0, 3))
+            };
+            board.shift(line, 1);
+            assert!(board.is_solvable());
+        }
+    }
+
+    #[test]
+    fn shuffled_loopover_boards_are_solvable() {
+        let mut rng = seeded_rng(21);
+        for &(w, h) in &[(3, 3), (4, 4), (5, 3), (1, 4)] {
+            for &mode in &[ShuffleMode::Uniform, ShuffleMode::Scramble] {
+                let mut board = loopover_board(w, h);
+                board.shuffle_with(mode, &mut rng);
+                assert!(board.is_solvable());
+                assert!(!board.is_solved());
+            }
+        }
+    }
+}
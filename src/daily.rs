@@ -0,0 +1,225 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use board::ShuffleMode;
+use grid::MoveMetric;
+use score::Score;
+use date;
+use save;
+
+
+//everyone gets the same daily board, so its settings are fixed
+pub const DAILY_WIDTH: u32 = 4;
+pub const DAILY_HEIGHT: u32 = 4;
+pub const DAILY_SHUFFLE: ShuffleMode = ShuffleMode::Uniform;
+
+
+//days since 1970-01-01 in UTC, so the day rolls over
+//at the same moment for everyone
+pub fn today() -> i64 {
+    (date::now_secs() / 86_400) as i64
+}
+
+//kept apart from the seeds people type in by hand
+pub fn seed_for(day: i64) -> u64 {
+    (day as u64) ^ 0xDA11_D00D_0000_0000
+}
+
+
+#[derive(Clone, Debug)]
+pub struct DailyEntry {
+    pub day: i64,
+    pub name: String,
+    pub metric: MoveMetric,
+    pub score: Score
+}
+
+
+//each player's best result for every day they played,
+//one per line as tab separated values
+pub struct DailyBook {
+    path: PathBuf,
+    entries: Vec<DailyEntry>
+}
+
+impl DailyBook {
+
+    pub fn load() -> DailyBook {
+        let mut book = DailyBook {
+            path: save::data_path("daily.txt"),
+            entries: Vec::new()
+        };
+
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(&book.path) {
+            if let Err(e) = file.read_to_string(&mut contents) {
+                println!("Couldn't read daily results: {}", e);
+            }
+        }
+
+        for (i, line) in contents.lines().enumerate() {
+            match DailyBook::parse_line(line) {
+                Some(entry) => book.entries.push(entry),
+                None => println!("Skipping bad daily result on line {}", i + 1)
+            }
+        }
+
+        book
+    }
+
+    //day, metric, moves, time, name. the name is last since it's
+    //the only thing that might have a tab in it
+    fn parse_line(line: &str) -> Option<DailyEntry> {
+        let fields: Vec<&str> = line.splitn(5, '\t').collect();
+        if fields.len() != 5 {
+            return None;
+        }
+
+        let day = fields[0].parse().ok()?;
+        Some(DailyEntry {
+            day: day,
+            metric: MoveMetric::from_key(fields[1])?,
+            score: Score {
+                moves: fields[2].parse().ok()?,
+                time_ms: fields[3].parse().ok()?,
+                seed: seed_for(day)
+            },
+            name: fields[4].to_string()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = File::create(&self.path)?;
+        for entry in &self.entries {
+            writeln!(file, "{}\t{}\t{}\t{}\t{}",
+                     entry.day,
+                     entry.metric.key(),
+                     entry.score.moves,
+                     entry.score.time_ms,
+                     entry.name)?;
+        }
+
+        Ok(())
+    }
+
+    //only the better result is kept if the day is played again. moves
+    //counted different ways can't be compared, so each metric is kept
+    pub fn add(&mut self, day: i64, name: &str, metric: MoveMetric, score: Score) {
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+
+        let existing = self.entries
+            .iter()
+            .position(|entry| entry.day == day && entry.name == name && entry.metric == metric);
+        match existing {
+            Some(i) => {
+                let old = &self.entries[i].score;
+                if (score.moves, score.time_ms) >= (old.moves, old.time_ms) {
+                    return;
+                }
+                self.entries[i].score = score;
+            },
+            None => self.entries.push(DailyEntry {
+                day: day,
+                name: name,
+                metric: metric,
+                score: score
+            })
+        }
+
+        if let Err(e) = self.save() {
+            println!("Couldn't save daily results: {}", e);
+        }
+    }
+
+    pub fn result(&self, day: i64, name: &str, metric: MoveMetric) -> Option<&DailyEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.day == day && entry.name == name && entry.metric == metric)
+    }
+
+    //counting moves either way
+    fn played(&self, day: i64, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.day == day && entry.name == name)
+    }
+
+    //days in a row played up to today. not having played yet
+    //today doesn't break the streak until the day is over
+    pub fn streak(&self, today: i64, name: &str) -> u32 {
+        let mut day = if self.played(today, name) {
+            today
+        } else {
+            today - 1
+        };
+
+        let mut streak = 0;
+        while self.played(day, name) {
+            streak += 1;
+            day -= 1;
+        }
+        streak
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //not saved anywhere, the save just fails
+    fn book() -> DailyBook {
+        DailyBook {
+            path: PathBuf::new(),
+            entries: Vec::new()
+        }
+    }
+
+    fn score(moves: u32, time_ms: u64) -> Score {
+        Score {
+            moves: moves,
+            time_ms: time_ms,
+            seed: 0
+        }
+    }
+
+    #[test]
+    fn keeps_the_better_result() {
+        let mut book = book();
+        book.add(100, "sam", MoveMetric::Mtm, score(40, 9000));
+        book.add(100, "sam", MoveMetric::Mtm, score(50, 5000));
+        assert_eq!(book.result(100, "sam", MoveMetric::Mtm).unwrap().score.moves, 40);
+        book.add(100, "sam", MoveMetric::Mtm, score(40, 8000));
+        assert_eq!(book.result(100, "sam", MoveMetric::Mtm).unwrap().score.time_ms, 8000);
+    }
+
+    #[test]
+    fn metrics_are_kept_apart() {
+        let mut book = book();
+        book.add(100, "sam", MoveMetric::Stm, score(60, 9000));
+        book.add(100, "sam", MoveMetric::Mtm, score(45, 9000));
+        assert_eq!(book.result(100, "sam", MoveMetric::Stm).unwrap().score.moves, 60);
+        assert_eq!(book.result(100, "sam", MoveMetric::Mtm).unwrap().score.moves, 45);
+        assert!(book.result(100, "alex", MoveMetric::Mtm).is_none());
+    }
+
+    #[test]
+    fn streak_counts_days_in_a_row() {
+        let mut book = book();
+        for &day in &[96, 98, 99] {
+            book.add(day, "sam", MoveMetric::Mtm, score(40, 9000));
+        }
+        book.add(100, "sam", MoveMetric::Stm, score(40, 9000));
+        assert_eq!(book.streak(100, "sam"), 3);
+        //today isn't over yet
+        assert_eq!(book.streak(101, "sam"), 3);
+        assert_eq!(book.streak(102, "sam"), 0);
+    }
+}
@@ -1,23 +1,436 @@
 
 extern crate opengl_graphics;
-extern crate rand;
 extern crate image;
 
+use self::image::{DynamicImage, GenericImage, ImageError};
 use piston::input::*;
 use opengl_graphics::{GlGraphics, GlyphCache};
 use graphics::*;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use board::{self, Board, BoardError, Direction, GoalLayout, Line, ShuffleMode, Variant};
+use pattern_db::{div_ceil, PatternDb};
+use solver;
+use score;
+use date;
+use save::SavedGame;
+use puzzle_text::{self, ParseError};
 
 
 pub enum GameEvent {
     Completed,
+    //ran out of moves in a move limit game
+    Failed,
     NoEvent
 }
 
 
+//why a game couldn't be set up
+#[derive(Debug)]
+pub enum LoadError {
+    Board(BoardError),
+    Parse(ParseError),
+    //the picture is missing or can't be read, with its path
+    Image(String, ImageError)
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Board(e) => write!(f, "{}", e),
+            LoadError::Parse(e) => write!(f, "{}", e),
+            LoadError::Image(path, e) => write!(f, "couldn't open image {}: {}", path, e)
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn description(&self) -> &str {
+        "couldn't set up the game"
+    }
+}
+
+impl From<BoardError> for LoadError {
+    fn from(e: BoardError) -> LoadError {
+        LoadError::Board(e)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(e: ParseError) -> LoadError {
+        LoadError::Parse(e)
+    }
+}
+
+
+//what a new game is set up with, picked on the title. games
+//are only ranked against others with the same settings
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameSettings {
+    pub x_cells: u32,
+    pub y_cells: u32,
+    pub blanks: u32,
+    pub goal: GoalLayout,
+    pub variant: Variant,
+    pub shuffle: ShuffleMode,
+    pub metric: MoveMetric,
+    pub img_path: String,
+    pub fit: FitMode
+}
+
+
+//how a slide of several tiles in a line is counted
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveMetric {
+    //multi tile, the whole line is one move
+    Mtm,
+    //single tile, every tile that moved is a move
+    Stm
+}
+
+impl MoveMetric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MoveMetric::Mtm => "MTM",
+            MoveMetric::Stm => "STM"
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            MoveMetric::Mtm => "mtm",
+            MoveMetric::Stm => "stm"
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<MoveMetric> {
+        match key {
+            "mtm" => Some(MoveMetric::Mtm),
+            "stm" => Some(MoveMetric::Stm),
+            _ => None
+        }
+    }
+
+    pub fn next(&self) -> MoveMetric {
+        match self {
+            MoveMetric::Mtm => MoveMetric::Stm,
+            MoveMetric::Stm => MoveMetric::Mtm
+        }
+    }
+
+    pub fn count(&self, tiles_moved: u32) -> u32 {
+        match self {
+            MoveMetric::Mtm => if tiles_moved > 0 { 1 } else { 0 },
+            MoveMetric::Stm => tiles_moved
+        }
+    }
+
+    //length of a list of single tile moves starting with the blank at
+    //empty. for MTM a run of tiles sliding the same way is one move
+    pub fn path_length(&self, board: &Board, moves: &[(u32, u32)]) -> u32 {
+        match self {
+            MoveMetric::Stm => moves.len() as u32,
+            MoveMetric::Mtm => {
+                let mut length = 0;
+                let mut last_dir = None;
+                let mut blank = board.empty();
+                for &cell in moves {
+                    //asking the board keeps moves across a wrapped edge right
+                    let dir = Direction::all()
+                        .iter()
+                        .cloned()
+                        .find(|&dir| board.step(blank, dir) == Some(cell));
+                    if last_dir != Some(dir) {
+                        length += 1;
+                    }
+                    last_dir = Some(dir);
+                    blank = cell;
+                }
+                length
+            }
+        }
+    }
+}
+
+
+//what the arrow keys move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyMode {
+    //the tile slides in the direction pressed
+    Tile,
+    //the blank moves in the direction pressed
+    Blank
+}
+
+impl KeyMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyMode::Tile => "Keys Move Tile",
+            KeyMode::Blank => "Keys Move Blank"
+        }
+    }
+
+    pub fn next(&self) -> KeyMode {
+        match self {
+            KeyMode::Tile => KeyMode::Blank,
+            KeyMode::Blank => KeyMode::Tile
+        }
+    }
+}
+
+
+//how long a move takes to play out on the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlideSpeed {
+    //no animation at all, for speedsolving
+    Instant,
+    Fast,
+    Normal,
+    Slow
+}
+
+impl SlideSpeed {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SlideSpeed::Instant => "Instant",
+            SlideSpeed::Fast => "Fast",
+            SlideSpeed::Normal => "Normal",
+            SlideSpeed::Slow => "Slow"
+        }
+    }
+
+    pub fn next(&self) -> SlideSpeed {
+        match self {
+            SlideSpeed::Instant => SlideSpeed::Fast,
+            SlideSpeed::Fast => SlideSpeed::Normal,
+            SlideSpeed::Normal => SlideSpeed::Slow,
+            SlideSpeed::Slow => SlideSpeed::Instant
+        }
+    }
+
+    //in seconds
+    pub fn duration(&self) -> f64 {
+        match self {
+            SlideSpeed::Instant => 0.0,
+            SlideSpeed::Fast => 0.06,
+            SlideSpeed::Normal => 0.12,
+            SlideSpeed::Slow => 0.25
+        }
+    }
+}
+
+
+//how a move speeds up and slows down as it plays out
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Easing {
+    //starts fast and settles into place, like a flicked tile
+    EaseOut,
+    //starts and ends slowly
+    EaseInOut
+}
+
+impl Easing {
+    //how far along the move is when t of its time has gone
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = clamp(t, 0.0, 1.0);
+        match self {
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+            }
+        }
+    }
+}
+
+
+//how a picture that isn't the shape of the board is fitted to it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FitMode {
+    //all of the picture, with black bars to fill the rest
+    Letterbox,
+    //fill the board, cutting off the sides or top and bottom
+    Crop,
+    //fill the board by squashing the picture
+    Stretch
+}
+
+impl FitMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FitMode::Letterbox => "Letterbox",
+            FitMode::Crop => "Crop",
+            FitMode::Stretch => "Stretch"
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            FitMode::Letterbox => "letterbox",
+            FitMode::Crop => "crop",
+            FitMode::Stretch => "stretch"
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<FitMode> {
+        match key {
+            "letterbox" => Some(FitMode::Letterbox),
+            "crop" => Some(FitMode::Crop),
+            "stretch" => Some(FitMode::Stretch),
+            _ => None
+        }
+    }
+
+    pub fn next(&self) -> FitMode {
+        match self {
+            FitMode::Letterbox => FitMode::Crop,
+            FitMode::Crop => FitMode::Stretch,
+            FitMode::Stretch => FitMode::Letterbox
+        }
+    }
+
+    //the picture at exactly width by height
+    pub fn fit(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        let filter = image::FilterType::Triangle;
+        let (img_w, img_h) = img.dimensions();
+        let ((w, h), (x, y)) = self.placement(img_w, img_h, width, height);
+        let mut resized = img.resize_exact(w, h, filter);
+
+        match self {
+            FitMode::Stretch => resized,
+            FitMode::Crop => resized.crop(x, y, width, height),
+            FitMode::Letterbox => {
+                let mut boxed = DynamicImage::new_rgba8(width, height);
+                boxed.copy_from(&resized, x, y);
+                boxed
+            }
+        }
+    }
+
+    //the size an img_w by img_h picture is scaled to for a width by
+    //height board, and where it's centered: the corner of the part
+    //kept when cropping, or where it goes on the board otherwise
+    pub fn placement(&self,
+                     img_w: u32,
+                     img_h: u32,
+                     width: u32,
+                     height: u32) -> ((u32, u32), (u32, u32)) {
+        if *self == FitMode::Stretch || img_w == 0 || img_h == 0 {
+            return ((width, height), (0, 0));
+        }
+
+        //wider than the board, compared without rounding
+        let wider = img_w as u64 * height as u64 > img_h as u64 * width as u64;
+        //the other side scaled to match, rounded up when cropping so
+        //it still covers the board and to nearest when letterboxing
+        let scaled = |side: u32, num: u32, den: u32, up: bool| -> u32 {
+            let (side, num, den) = (side as u64, num as u64, den as u64);
+            let size = if up {
+                div_ceil(side * num, den)
+            } else {
+                (side * num + den / 2) / den
+            };
+            size.max(1) as u32
+        };
+
+        match self {
+            FitMode::Crop => {
+                let (w, h) = if wider {
+                    (scaled(img_w, height, img_h, true), height)
+                } else {
+                    (width, scaled(img_h, width, img_w, true))
+                };
+                ((w, h), ((w - width) / 2, (h - height) / 2))
+            },
+            _ => {
+                let (w, h) = if wider {
+                    (width, scaled(img_h, width, img_w, false).min(height))
+                } else {
+                    (scaled(img_w, height, img_h, false).min(width), height)
+                };
+                ((w, h), ((width - w) / 2, (height - h) / 2))
+            }
+        }
+    }
+}
+
+
+//v kept between lo and hi
+fn clamp<T: PartialOrd>(v: T, lo: T, hi: T) -> T {
+    if v < lo {
+        lo
+    } else if v > hi {
+        hi
+    } else {
+        v
+    }
+}
+
+//where the ith of count equal parts of size starts and how long it is.
+//the parts can differ by a pixel, but every pixel is in exactly one
+pub fn span(i: u32, count: u32, size: u32) -> (u32, u32) {
+    let start = (i as u64 * size as u64 / count as u64) as u32;
+    let end = ((i + 1) as u64 * size as u64 / count as u64) as u32;
+    (start, end - start)
+}
+
+//which of those parts pos is in, going past either
+//end when it's outside so drags off the board still count
+fn part_at(pos: f64, count: u32, size: u32) -> i32 {
+    if pos < 0.0 || pos >= size as f64 {
+        return (pos * count as f64 / size.max(1) as f64).floor() as i32;
+    }
+    (0..count)
+        .find(|&i| {
+            let (start, len) = span(i, count, size);
+            pos < (start + len) as f64
+        })
+        .unwrap_or(count - 1) as i32
+}
+
+
+//the rectangle of the window the board is drawn in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Area {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32
+}
+
+impl Area {
+    //the part of it that cell (x, y) of an x_cells by y_cells board takes
+    //up, in whole pixels the same way the picture was cut up
+    pub fn cell(&self, x: u32, y: u32, x_cells: u32, y_cells: u32) -> Area {
+        let (x_offset, w) = span(x, x_cells, self.w);
+        let (y_offset, h) = span(y, y_cells, self.h);
+        Area {
+            x: self.x + x_offset,
+            y: self.y + y_offset,
+            w: w,
+            h: h
+        }
+    }
+}
+
+//height of the bar along the bottom with the moves and time
+const HUD_HEIGHT: u32 = 40;
+
+
 pub struct Cell {
-    pub x_pos: u32, 
-    pub y_pos: u32,
-    pub content:Option<u32>
+    pub content:Option<u32>,
+    //quarter turns clockwise
+    pub turns: u8,
+    //how far from its cell a moving tile is drawn, in cells,
+    //and how many more quarter turns a turning one is drawn at
+    pub shift: (f64, f64),
+    pub spin: f64,
+    pub highlight: bool
 }
 
 
@@ -26,29 +439,38 @@ impl Cell {
     pub fn render(&self, 
                   gl: &mut GlGraphics, 
                   t: &math::Matrix2d,
-                  x_cells: u32,
-                  y_cells: u32,
                   glyph: &mut GlyphCache,
-                  args: &RenderArgs,
+                  rect: &Area,
+                  draw_state: &DrawState,
                   texture: Option<&opengl_graphics::Texture>) {
 
-        let width = args.width as f32 / x_cells as f32;
-        let height = args.height as f32 / y_cells as f32;
-        let x_offset = width * self.x_pos as f32;
-        let y_offset = height * self.y_pos as f32;
+        //rect is the cell's own part of the board
+        let (x_offset, width) = (rect.x as f32, rect.w as f32);
+        let (y_offset, height) = (rect.y as f32, rect.h as f32);
 
-        let transform = t.trans(x_offset as f64,
-                                y_offset as f64);
+        let transform = t.trans(x_offset as f64 + self.shift.0 * width as f64,
+                                y_offset as f64 + self.shift.1 * height as f64);
 
 
         match &texture {
             Some(val) => {
                 let img: &opengl_graphics::Texture = val;
+                //turned on its side the tile is squeezed
+                //to fit the cell the other way round
+                let (box_w, box_h) = if self.turns % 2 == 1 {
+                    (height as f64, width as f64)
+                } else {
+                    (width as f64, height as f64)
+                };
+                let turned = transform
+                    .trans(width as f64 / 2.0, height as f64 / 2.0)
+                    .rot_deg(90.0 * (self.turns as f64 + self.spin))
+                    .trans(-box_w / 2.0, -box_h / 2.0);
                 let (scale_x, scale_y) = (
-                    width as f64 / img.get_width() as f64,
-                    height as f64 / img.get_height() as f64
+                    box_w / img.get_width() as f64,
+                    box_h / img.get_height() as f64
                 );
-                image(img, transform.scale(
+                Image::new().draw(img, draw_state, turned.scale(
                         scale_x, 
                         scale_y
                 ), gl);
@@ -62,46 +484,140 @@ impl Cell {
 
                 text::Text::new_color(text_color, 24).draw(&text_content,
                                                            glyph,
-                                                           &DrawState::default(),
-                                                           transform.trans(
+                                                           draw_state,
+                                                           turned.trans(
                                                                5.0, 25.0
                                                            ),
                                                            gl).unwrap();
+
+                if self.highlight {
+                    let square = rectangle::square(0.0,0.0,1.0);
+                    let col:[f32; 4] = [1.0, 0.9, 0.2, 0.5];
+                    Rectangle::new(col).draw(square, draw_state, transform.scale(
+                            width as f64,
+                            height as f64
+                    ), gl);
+                }
             },
             None => {
                 let square = rectangle::square(0.0,0.0,1.0);
                 let col:[f32; 4] = [0.1, 0.1, 0.1, 1.0];
-                rectangle(col, square, transform.scale(
+                Rectangle::new(col).draw(square, draw_state, transform.scale(
                         width as f64,
                         height as f64
                 ), gl);
             }
         }
     }
+}
+
+
+//a move as it goes in the history
+#[derive(Clone, Copy)]
+enum Move {
+    //the blank that moved, which way and how far
+    Slide((u32, u32), Direction, u32),
+    //a tile turned a quarter clockwise
+    Rotate((u32, u32)),
+    //a loopover row or column turned round
+    Shift(Line, i32)
+}
 
-    pub fn update(&mut self) {
-    }
-    
-    pub fn correct_spot(&self, expect_content: Option<u32>) -> bool { 
-        self.content == expect_content
-    }
 
-    pub fn click(&mut self) {
-        //println!("Clicked Cell ({}, {})", self.x_pos, self.y_pos);
+//a move being played out. the board has already made it,
+//the tiles are just drawn coming from where they were
+struct Animation {
+    elapsed: f64,
+    duration: f64,
+    easing: Easing,
+    //cells whose tiles moved, and how far back they started in cells
+    moved: Vec<((u32, u32), (f64, f64))>,
+    //a tile that turned, and how far back it started in quarter turns
+    turned: Option<((u32, u32), f64)>
+}
+
+impl Animation {
+    //how much of the way the tiles still have to go, 1 down to 0
+    fn left(&self) -> f64 {
+        1.0 - self.easing.apply(self.elapsed / self.duration)
     }
 }
 
 
+//input that came in while a move was still playing out,
+//kept so it can be made once the board catches up
+#[derive(Clone, Copy)]
+enum Input {
+    //a cell, and the ways to look for a blank from it in order
+    Click((u32, u32), [Direction; 4]),
+    Key(Direction),
+    Rotate((u32, u32)),
+    //a loopover row or column and how far to turn it
+    Shift(Line, i32),
+    Undo,
+    Redo
+}
+
+
 pub struct Grid {
     pub x_cells: u32,
     pub y_cells: u32,
-    empty_x: u32,
-    empty_y: u32,
+    board: Board,
+    //the picture as it was opened, so it can be cut up
+    //again at a new size when the window changes
+    picture: DynamicImage,
     img_tiles: Vec<opengl_graphics::Texture>,
-    cells: Vec<Cell>,
-    pub move_counter: u32
+    //the board size the tiles were cut for
+    tiles_size: (u32, u32),
+    pub move_counter: u32,
+    pub metric: MoveMetric,
+    pub key_mode: KeyMode,
+    pub shuffle: ShuffleMode,
+    pub img_path: String,
+    pub fit: FitMode,
+    pub player_name: String,
+    //seconds since the first move, the clock starts on that move
+    elapsed: f64,
+    timer_running: bool,
+    pub seed: u64,
+    history: Vec<Move>,
+    //moves that were undone, most recent last
+    redo_stack: Vec<Move>,
+    //the cell under the mouse, for turning tiles from the keyboard
+    hover: Option<(u32, u32)>,
+    //where a loopover drag started
+    drag_start: Option<(u32, u32)>,
+    pub speed: SlideSpeed,
+    animation: Option<Animation>,
+    queue: VecDeque<Input>,
+    //set once the game has had help, so it can't set a high score
+    pub assisted: bool,
+    //the day this is the daily puzzle for, if it is one
+    pub daily: Option<i64>,
+    solver_rx: Option<mpsc::Receiver<Option<Vec<(u32, u32)>>>>,
+    //set to stop the running solver
+    solver_cancel: Arc<AtomicBool>,
+    //the running solver is only after the next move
+    hint_pending: bool,
+    //cell to highlight and how much longer to show it
+    hint: Option<((u32, u32), f64)>,
+    auto_moves: Vec<(u32, u32)>,
+    auto_timer: f64,
+    //slack over an optimal solution picked on the title, kept
+    //for going back to it even if this board can't have a limit
+    pub move_limit: Option<u32>,
+    //the most moves the game can take, once it's been worked out
+    move_budget: Option<u32>,
+    budget_rx: Option<mpsc::Receiver<Option<u32>>>,
+    //set to stop working out the budget
+    budget_cancel: Arc<AtomicBool>
 }
 
+//seconds between each move while auto solving
+const AUTO_SOLVE_DELAY: f64 = 0.25;
+//seconds a hint stays highlighted
+const HINT_TIME: f64 = 1.5;
+
 impl Grid {
     //functions for grid
     pub fn render(&self, 
@@ -114,232 +630,786 @@ impl Grid {
 
         clear(BLACK, gl);
 
+        //the tiles are cut once the window has room for them, so a game
+        //started in a window with none has nothing to draw the board with
+        if !self.img_tiles.is_empty() {
+            self.render_tiles(gl, t, glyph, args);
+        }
+
+        self.render_hud(gl, t, glyph, args);
+    }
+
+    fn render_tiles(&self,
+                    gl: &mut GlGraphics,
+                    t: &math::Matrix2d,
+                    glyph: &mut GlyphCache,
+                    args: &RenderArgs) {
+        let area = self.area(args.width, args.height);
+        //tiles sliding in from across a wrapped edge are cut off at
+        //the board. the scissor is in pixels, which can be smaller
+        let scale = args.draw_width as f64 / args.width.max(1) as f64;
+        let draw_state = DrawState::default().scissor([
+            (area.x as f64 * scale) as u32,
+            (area.y as f64 * scale) as u32,
+            (area.w as f64 * scale).ceil() as u32,
+            (area.h as f64 * scale).ceil() as u32
+        ]);
+        let wraps = self.board.wraps() || self.board.loops();
+
+        //tiles that are still get drawn first so moving ones go over
+        //them, and the cells moving tiles are headed for start out empty
+        for moving in &[false, true] {
+            for (i, content) in self.board.cells().iter().enumerate() {
+                let (x_pos, y_pos) = (i as u32 % self.x_cells, i as u32 / self.x_cells);
+                let (shift, spin) = self.motion((x_pos, y_pos));
+                let is_moving = content.is_some() && (shift != (0.0, 0.0) || spin != 0.0);
+                if *moving && !is_moving {
+                    continue;
+                }
+
+                let highlight = match self.hint {
+                    Some((cell, _)) => cell == (x_pos, y_pos),
+                    None => false
+                };
+                let mut cell = Cell {
+                    content: if is_moving && !*moving { None } else { *content },
+                    turns: self.board.turn(x_pos, y_pos),
+                    shift: shift,
+                    spin: spin,
+                    highlight: highlight
+                };
+                let texture = match cell.content {
+                    Some(i) => Some(self.get_tile(i)),
+                    None => None
+                };
+                let rect = area.cell(x_pos, y_pos, self.x_cells, self.y_cells);
+                cell.render(gl,
+                            t,
+                            glyph,
+                            &rect,
+                            &draw_state,
+                            texture
+                );
+
+                //the part that's gone over the edge shows on the other side
+                if *moving && wraps && shift != (0.0, 0.0) {
+                    let across = |d: f64, cells: u32| if d > 0.0 {
+                        d - cells as f64
+                    } else if d < 0.0 {
+                        d + cells as f64
+                    } else {
+                        0.0
+                    };
+                    cell.shift = (across(shift.0, self.x_cells), across(shift.1, self.y_cells));
+                    cell.render(gl,
+                                t,
+                                glyph,
+                                &rect,
+                                &draw_state,
+                                texture
+                    );
+                }
+            }
+        }
+    }
+
+    //how far from its cell the tile there is drawn and how much
+    //more it's turned, while a move is being played out
+    fn motion(&self, cell: (u32, u32)) -> ((f64, f64), f64) {
+        let animation = match &self.animation {
+            Some(animation) => animation,
+            None => return ((0.0, 0.0), 0.0)
+        };
+        let left = animation.left();
+
+        let shift = animation.moved
+            .iter()
+            .find(|&&(moved, _)| moved == cell)
+            .map(|&(_, (dx, dy))| (dx * left, dy * left))
+            .unwrap_or((0.0, 0.0));
+        let spin = match animation.turned {
+            Some((turned, from)) if turned == cell => from * left,
+            _ => 0.0
+        };
+        (shift, spin)
+    }
+
+    fn render_hud(&self,
+                  gl: &mut GlGraphics,
+                  t: &math::Matrix2d,
+                  glyph: &mut GlyphCache,
+                  args: &RenderArgs) {
+        let bar_height = HUD_HEIGHT as f64;
+        let top = args.height as f64 - bar_height;
+
+        rectangle([0.0, 0.0, 0.0, 0.6],
+                  [0.0, top, args.width as f64, bar_height],
+                  *t,
+                  gl);
+
+        let mut lines = [
+            format!("Moves: {} ({})  Time: {}",
+                    self.move_counter,
+                    self.metric.name(),
+                    score::format_time(self.elapsed_ms())),
+            match self.daily {
+                Some(day) => format!("Daily: {}", date::format_date(day as u64 * 86_400)),
+                None => format!("Seed: {}", self.seed)
+            }
+        ];
+        if self.budget_rx.is_some() {
+            lines[1].push_str("  Moves left: ...");
+        } else if let Some(left) = self.moves_left() {
+            lines[1].push_str(&format!("  Moves left: {}", left));
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            text::Text::new_color([1.0, 1.0, 1.0, 1.0], 16).draw(line,
+                                                                 glyph,
+                                                                 &DrawState::default(),
+                                                                 t.trans(5.0, top + 17.0 + 18.0 * i as f64),
+                                                                 gl).unwrap();
+        }
+    }
+
+    pub fn update(&mut self, args: &UpdateArgs) -> GameEvent {
+        if self.timer_running {
+            self.elapsed += args.dt;
+        }
+
+        let finished = match &mut self.animation {
+            Some(animation) => {
+                animation.elapsed += args.dt;
+                animation.elapsed >= animation.duration
+            },
+            None => false
+        };
+        if finished {
+            self.animation = None;
+            match self.check_completed() {
+                GameEvent::NoEvent => {},
+                event => {
+                    self.queue.clear();
+                    return event;
+                }
+            }
+        }
+
+        //then whatever came in while it played, until
+        //one of them starts playing out in turn
+        while self.animation.is_none() {
+            let input = match self.queue.pop_front() {
+                Some(input) => input,
+                None => break
+            };
+            match self.apply(input) {
+                GameEvent::NoEvent => {},
+                event => {
+                    self.queue.clear();
+                    return event;
+                }
+            }
+        }
+
+        let mut budget = None;
+        if let Some(rx) = &self.budget_rx {
+            if let Ok(result) = rx.try_recv() {
+                budget = Some(result);
+            }
+        }
+        if let Some(result) = budget {
+            self.budget_rx = None;
+            self.move_budget = result;
+            if let Some(budget) = result {
+                println!("Move limit: {}", budget);
+            }
+
+            //the player may have used them up while waiting
+            if let GameEvent::Failed = self.check_completed() {
+                return GameEvent::Failed;
+            }
+        }
+
+        let mut solution = None;
+        if let Some(rx) = &self.solver_rx {
+            if let Ok(result) = rx.try_recv() {
+                solution = Some(result);
+            }
+        }
 
-        for cell in &self.cells {
-            let texture = match cell.content {
-                Some(i) => Some(self.get_tile(i)),
-                None => None
+        if let Some((cell, time_left)) = self.hint {
+            let time_left = time_left - args.dt;
+            self.hint = if time_left > 0.0 {
+                Some((cell, time_left))
+            } else {
+                None
             };
-            cell.render(gl,
-                        t,
-                        self.x_cells,
-                        self.y_cells,
-                        glyph,
-                        args,
-                        texture
-            );
         }
 
+        if let Some(result) = solution {
+            self.solver_rx = None;
+            match result {
+                Some(moves) if self.hint_pending => {
+                    self.hint_pending = false;
+                    if let Some(&cell) = moves.first() {
+                        self.hint = Some((cell, HINT_TIME));
+                    }
+                },
+                Some(mut moves) => {
+                    println!("Solution found in {} moves", moves.len());
+                    moves.reverse();
+                    self.auto_moves = moves;
+                    self.auto_timer = 0.0;
+                },
+                None => {println!("No solution found");}
+            }
+        }
+
+        if self.auto_moves.is_empty() {
+            return GameEvent::NoEvent;
+        }
+
+        //each move waits for the one before to play out
+        self.auto_timer += args.dt;
+        while self.auto_timer >= AUTO_SOLVE_DELAY && self.animation.is_none() {
+            self.auto_timer -= AUTO_SOLVE_DELAY;
+            if let Some((x, y)) = self.auto_moves.pop() {
+                self.slide(x, y, Direction::all());
+            }
+
+            match self.settle() {
+                GameEvent::NoEvent => {},
+                event => {
+                    self.auto_moves.clear();
+                    return event;
+                }
+            }
+        }
+
+        GameEvent::NoEvent
     }
 
-    pub fn update(&mut self) {
 
-        for c in &mut self.cells {
-            c.update();
+    //solve the board on another thread and play the moves back from
+    //update once it's done. not while moves are still playing out or
+    //waiting to, the board would have changed by the time they're found
+    pub fn auto_solve(&mut self, db: Option<Arc<PatternDb>>) {
+        if self.solver_rx.is_some() || !self.auto_moves.is_empty() || self.is_busy() {
+            return;
         }
+        if !self.can_solve(db.as_deref()) {
+            return;
+        }
+
+        println!("Solving...");
+        self.start_solver(db);
     }
 
+    //highlight the next move of a shortest solution
+    pub fn hint(&mut self, db: Option<Arc<PatternDb>>) {
+        if self.solver_rx.is_some() || !self.auto_moves.is_empty() || self.is_busy() {
+            return;
+        }
+        if !self.can_solve(db.as_deref()) {
+            return;
+        }
 
-    fn get_grid_index(x: u32, y: u32, w: u32) -> u32{
-        x + y * w
+        println!("Finding hint...");
+        self.hint_pending = true;
+        self.start_solver(db);
     }
 
+    fn can_solve(&self, db: Option<&PatternDb>) -> bool {
+        match self.unsolvable_reason(db) {
+            Some(reason) => {
+                println!("{}", reason);
+                false
+            },
+            None => true
+        }
+    }
 
-    fn is_adjacent(a: (u32, u32), b: (u32, u32)) -> bool {
-        let (ax, ay) = a;
-        let (bx, by) = b;
+    fn unsolvable_reason(&self, db: Option<&PatternDb>) -> Option<&'static str> {
+        if self.board.loops() {
+            Some("The solver doesn't play loopover")
+        } else if self.board.blank_count() > 1 {
+            Some("The solver only works with one blank")
+        } else if self.board.rotates() {
+            Some("The solver doesn't turn tiles")
+        } else if !solver::is_practical(&self.board, db) {
+            Some("No pattern database for this board, it's too big to solve without one")
+        } else {
+            None
+        }
+    }
 
-        ((ax as i32 - bx as i32).abs() == 1 &&
-         (ay == by)) ||
-            ((ay as i32 - by as i32).abs() == 1 &&
-             (ax == bx))
+    fn start_solver(&mut self, db: Option<Arc<PatternDb>>) {
+        self.assisted = true;
+
+        let board = self.board.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.solver_cancel = cancel.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            //the game may have been left before this finishes
+            let _ = tx.send(solver::solve_with(&board, db.as_deref(), &cancel));
+        });
+        self.solver_rx = Some(rx);
     }
 
+    //the budget is the moves taken so far plus an optimal solution from
+    //here plus some slack. it's worked out on another thread, and moves
+    //made in the meantime count against it. for MTM this is the length
+    //of the shortest STM solution, which can be a little over the best.
+    //boards the solver can't do get no limit, and the reason is returned
+    pub fn set_move_limit(&mut self, slack: u32, db: Option<Arc<PatternDb>>) -> Result<(), &'static str> {
+        self.move_limit = Some(slack);
+        if let Some(reason) = self.unsolvable_reason(db.as_deref()) {
+            return Err(reason);
+        }
 
-    fn swap_cells(&mut self, a: (u32, u32), b: (u32, u32)) {
-        let (ax, ay) = a;
-        let (bx, by) = b;
+        let board = self.board.clone();
+        let metric = self.metric;
+        let used = self.move_counter;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.budget_cancel = cancel.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let budget = solver::solve_with(&board, db.as_deref(), &cancel)
+                .map(|moves| used + metric.path_length(&board, &moves) + slack);
+            let _ = tx.send(budget);
+        });
+        self.budget_rx = Some(rx);
+        Ok(())
+    }
 
-        let a_index = Grid::get_grid_index(ax,ay,self.x_cells);
-        let b_index = Grid::get_grid_index(bx,by,self.x_cells);
+    pub fn moves_left(&self) -> Option<u32> {
+        self.move_budget.map(|budget| budget.saturating_sub(self.move_counter))
+    }
 
-        let a_cell_content = self.cells
-            .get(a_index as usize)
-            .unwrap()
-            .content;
+    //stop any solving, the board is about to change
+    pub fn cancel_auto_solve(&mut self) {
+        self.solver_cancel.store(true, Ordering::Relaxed);
+        self.solver_rx = None;
+        self.hint_pending = false;
+        self.hint = None;
+        self.auto_moves.clear();
+    }
 
-        let b_cell_content = self.cells
-            .get(b_index as usize)
-            .unwrap()
-            .content;
 
-        self.cells.get_mut(b_index as usize).unwrap().content = a_cell_content;
-        self.cells.get_mut(a_index as usize).unwrap().content = b_cell_content;
+    //the game is being left, so nothing still searching is needed
+    pub fn stop_solving(&self) {
+        self.solver_cancel.store(true, Ordering::Relaxed);
+        self.budget_cancel.store(true, Ordering::Relaxed);
     }
 
 
-    pub fn click(&mut self, x_cell: u32, y_cell: u32) -> GameEvent {
-        let index = Grid::get_grid_index(x_cell,y_cell,self.x_cells);
-        if let Some(cell) = self.cells.get_mut(index as usize) {
-            cell.click();
-        }
+    pub fn elapsed_ms(&self) -> u64 {
+        (self.elapsed * 1000.0).round() as u64
+    }
 
-        let (move_to_x, move_to_y) = (self.empty_x, self.empty_y);
 
-        //real code
-        if Grid::is_adjacent((x_cell, y_cell),
-        (move_to_x, move_to_y)) {
-            println!("Clicked next to empty!");
+    pub fn blank_count(&self) -> u32 {
+        self.board.blank_count() as u32
+    }
 
-            self.swap_cells((x_cell, y_cell),
-            (move_to_x, move_to_y));
+    pub fn goal(&self) -> GoalLayout {
+        self.board.goal()
+    }
 
-            self.empty_x = x_cell;
-            self.empty_y = y_cell;
+    pub fn variant(&self) -> Variant {
+        self.board.variant()
+    }
 
-            self.move_counter += 1;
+
+    //a new move, which throws away anything that could be redone.
+    //any cell in line with a blank slides the tiles up to it, towards
+    //the blank that comes first in order when two are as near
+    fn slide(&mut self, x_cell: u32, y_cell: u32, order: [Direction; 4]) -> bool {
+        match self.board.line_blank(x_cell, y_cell, order) {
+            Some((blank, dir, steps)) => self.slide_blank(blank, dir, steps),
+            None => false
+        }
+    }
+
+    fn slide_blank(&mut self, blank: (u32, u32), dir: Direction, steps: u32) -> bool {
+        let moved = self.board.slide_blank(blank, dir, steps);
+        if moved == 0 {
+            return false;
         }
 
-        if self.empty_x == self.x_cells - 1 &&
-           self.empty_y == self.y_cells - 1 &&
-           self.is_solved() {
+        self.animate_slide(blank, dir, steps);
+        self.history.push(Move::Slide(blank, dir, steps));
+        self.redo_stack.clear();
+        self.move_counter += self.metric.count(moved);
+        self.timer_running = true;
+        true
+    }
+
+    fn check_completed(&mut self) -> GameEvent {
+        if self.board.is_solved() {
+            self.timer_running = false;
             return GameEvent::Completed;
         }
 
+        if let Some(0) = self.moves_left() {
+            self.timer_running = false;
+            return GameEvent::Failed;
+        }
+
         GameEvent::NoEvent
     }
 
-    pub fn mouse_move(&mut self, raw_x: u32, raw_y: u32, w: u32, h: u32) {
-        
+    //the clock stops as soon as the last move is made, but
+    //the game isn't over until that move has played out
+    fn settle(&mut self) -> GameEvent {
+        let event = self.check_completed();
+        if self.animation.is_some() {
+            return GameEvent::NoEvent;
+        }
+        event
     }
 
-    pub fn is_solved(&self) -> bool {
-        for cell in &self.cells {
-            let (cx, cy) = (
-                    cell.x_pos,
-                    cell.y_pos
-                );
-            let expect = if cx == self.x_cells - 1 &&
-                            cy == self.y_cells - 1 {
-                            None
-                        } else {
-                            Some(Grid::get_grid_index(cx,cy,self.x_cells))
-                        };
-            if !cell.correct_spot(expect) {
-                return false;
-            }   
+    fn shift(&mut self, line: Line, amount: i32) -> bool {
+        let moved = self.board.shift(line, amount);
+        if moved == 0 {
+            return false;
         }
 
+        self.animate_shift(line, amount);
+        self.history.push(Move::Shift(line, amount));
+        self.redo_stack.clear();
+        self.move_counter += self.metric.count(moved);
+        self.timer_running = true;
         true
     }
 
-    pub fn get_tile(&self, index: u32) -> &opengl_graphics::Texture {
-        self.img_tiles.get(index as usize).unwrap()
+    //turning a tile is a move of its own, under either metric
+    fn turn_tile(&mut self, x_cell: u32, y_cell: u32) {
+        if self.board.rotate(x_cell, y_cell, 1) {
+            self.animate_turn((x_cell, y_cell), -1.0);
+            self.history.push(Move::Rotate((x_cell, y_cell)));
+            self.redo_stack.clear();
+            self.move_counter += self.metric.count(1);
+            self.timer_running = true;
+        }
     }
 
+    fn key_slide(&mut self, dir: Direction) {
+        //the tile that moves is on the opposite side of the
+        //blank when the tile is the one going in that direction
+        let dir = match self.key_mode {
+            KeyMode::Tile => dir.opposite(),
+            KeyMode::Blank => dir
+        };
 
-    //generate all neighbouring states, 
-    //and go to one of the neighbouring states
-    //at random
-    pub fn random_step<R: rand::Rng>(&mut self, rng: &mut R) {
-        let mut possible_moves: Vec<(u32, u32)> = Vec::new();
-        //up, down, left, right
+        //the first blank that has a tile on that side
+        for blank in self.board.blanks() {
+            if self.slide_blank(blank, dir, 1) {
+                break;
+            }
+        }
+    }
 
-        let (ex, ey) = (self.empty_x, self.empty_y);
+    //undoing slides the line back, so it counts as a move
+    //like any other and the game still counts for a high score
+    fn undo_move(&mut self) {
+        if let Some(last) = self.history.pop() {
+            let moved = match last {
+                //the blank ended up steps away, so send it back
+                Move::Slide(blank, dir, steps) => match self.board.walk(blank, dir, steps) {
+                    Some(end) => {
+                        let moved = self.board.slide_blank(end, dir.opposite(), steps);
+                        self.animate_slide(end, dir.opposite(), steps);
+                        moved
+                    },
+                    None => 0
+                },
+                Move::Rotate((x, y)) => if self.board.rotate(x, y, 3) {
+                    self.animate_turn((x, y), 1.0);
+                    1
+                } else {
+                    0
+                },
+                Move::Shift(line, amount) => {
+                    let moved = self.board.shift(line, -amount);
+                    self.animate_shift(line, -amount);
+                    moved
+                }
+            };
+            self.redo_stack.push(last);
+            self.move_counter += self.metric.count(moved);
+        }
+    }
 
-        //up
-        if ey > 0 {
-            let mv = (
-                ex,
-                ey - 1
-            );
-            possible_moves.push(mv);
+    fn redo_move(&mut self) {
+        if let Some(next) = self.redo_stack.pop() {
+            let moved = match next {
+                Move::Slide(blank, dir, steps) => {
+                    let moved = self.board.slide_blank(blank, dir, steps);
+                    self.animate_slide(blank, dir, steps);
+                    moved
+                },
+                Move::Rotate((x, y)) => if self.board.rotate(x, y, 1) {
+                    self.animate_turn((x, y), -1.0);
+                    1
+                } else {
+                    0
+                },
+                Move::Shift(line, amount) => {
+                    let moved = self.board.shift(line, amount);
+                    self.animate_shift(line, amount);
+                    moved
+                }
+            };
+            self.history.push(next);
+            self.move_counter += self.metric.count(moved);
         }
+    }
+
+    //play out the blank going steps in dir. the tiles it passed now sit
+    //where it went through, each come from one cell further along
+    fn animate_slide(&mut self, blank: (u32, u32), dir: Direction, steps: u32) {
+        let (dx, dy) = dir.delta();
+        let moved = (0..steps)
+            .filter_map(|i| self.board.walk(blank, dir, i))
+            .map(|cell| (cell, (dx as f64, dy as f64)))
+            .collect();
+        self.animate(moved, None, Easing::EaseOut);
+    }
 
-        //down
-        if ey < self.y_cells - 1 {
-            let mv = (
-                ex,
-                ey + 1
-            );
-            possible_moves.push(mv);
+    //play out a loopover line turning round, the short way
+    fn animate_shift(&mut self, line: Line, amount: i32) {
+        let (len, cells): (i32, Vec<(u32, u32)>) = match line {
+            Line::Row(y) => (self.x_cells as i32, (0..self.x_cells).map(|x| (x, y)).collect()),
+            Line::Column(x) => (self.y_cells as i32, (0..self.y_cells).map(|y| (x, y)).collect())
+        };
+        let mut amount = amount % len;
+        if amount > len / 2 {
+            amount -= len;
+        } else if amount < -len / 2 {
+            amount += len;
         }
 
-        //left
-        if ex > 0 {
-            let mv = (
-                ex - 1,
-                ey
-            );
-            possible_moves.push(mv);
+        let back = -amount as f64;
+        let moved = cells
+            .into_iter()
+            .map(|cell| match line {
+                Line::Row(_) => (cell, (back, 0.0)),
+                Line::Column(_) => (cell, (0.0, back))
+            })
+            .collect();
+        self.animate(moved, None, Easing::EaseInOut);
+    }
+
+    fn animate_turn(&mut self, cell: (u32, u32), from: f64) {
+        self.animate(Vec::new(), Some((cell, from)), Easing::EaseInOut);
+    }
+
+    //a new move snaps any that's still playing into place
+    fn animate(&mut self,
+               moved: Vec<((u32, u32), (f64, f64))>,
+               turned: Option<((u32, u32), f64)>,
+               easing: Easing) {
+        let duration = self.speed.duration();
+        self.animation = if duration > 0.0 {
+            Some(Animation {
+                elapsed: 0.0,
+                duration: duration,
+                easing: easing,
+                moved: moved,
+                turned: turned
+            })
+        } else {
+            None
+        };
+    }
+
+    //a move is still playing out or waiting for one to
+    pub fn is_busy(&self) -> bool {
+        self.animation.is_some() || !self.queue.is_empty()
+    }
+
+    //input waits its turn while a move plays out, so fast clicks
+    //and key presses all get made, one after another
+    fn input(&mut self, input: Input) -> GameEvent {
+        if self.is_busy() {
+            self.queue.push_back(input);
+            return GameEvent::NoEvent;
         }
+        self.apply(input)
+    }
 
-        //right
-        if ex < self.x_cells - 1 {
-            let mv = (
-                ex + 1,
-                ey
-            );
-            possible_moves.push(mv);
+    fn apply(&mut self, input: Input) -> GameEvent {
+        match input {
+            Input::Click((x, y), order) => if self.slide(x, y, order) {
+                println!("Clicked next to empty!");
+            },
+            Input::Key(dir) => self.key_slide(dir),
+            Input::Rotate((x, y)) => self.turn_tile(x, y),
+            Input::Shift(line, amount) => {self.shift(line, amount);},
+            Input::Undo => self.undo_move(),
+            Input::Redo => self.redo_move()
         }
 
-        let rand_move = rng.choose(&possible_moves);
-        //println!("Moving:{:?}", &rand_move);
+        self.settle()
+    }
 
-        if let Some(mv) = rand_move {
-            self.swap_cells(*mv, (ex, ey));
-            let (mx, my) = *mv;
-            self.empty_x = mx;
-            self.empty_y = my;
+    //offset is where in the cell the click was, see offset_in_cell.
+    //with a blank as near either way, the tile goes the way clicked
+    pub fn click(&mut self, x_cell: u32, y_cell: u32, offset: (f64, f64)) -> GameEvent {
+        self.cancel_auto_solve();
+
+        //loopover moves happen when the drag is let go
+        if self.board.loops() {
+            self.drag_start = Some((x_cell, y_cell));
+            return GameEvent::NoEvent;
         }
+
+        self.input(Input::Click((x_cell, y_cell), Direction::towards(offset.0, offset.1)))
     }
 
-    //begin at end state, do a random graph transversal
-    //with the specified depth
-    pub fn randomize(&mut self, depth: u32) {
-        //cells, empty_x, empty_y
-        //swap_cells
-        println!("Randomizing...");
-        let mut rng = rand::thread_rng();
-        for i in 0..depth {
-            self.random_step(&mut rng);
+    //a drag along a row turns that row, and down a column turns the
+    //column, by however many cells the mouse went
+    pub fn release(&mut self, x_cell: i32, y_cell: i32) -> GameEvent {
+        let (start_x, start_y) = match self.drag_start.take() {
+            Some(start) => start,
+            None => return GameEvent::NoEvent
+        };
+
+        let (dx, dy) = (x_cell - start_x as i32, y_cell - start_y as i32);
+        if dx.abs() >= dy.abs() {
+            self.input(Input::Shift(Line::Row(start_y), dx))
+        } else {
+            self.input(Input::Shift(Line::Column(start_x), dy))
         }
-        println!("Randomized...");
     }
 
-    pub fn new (x_cells: u32, 
-                y_cells: u32, 
-                width: u32, 
-                height: u32, 
-                img_path: &str) -> Grid {
+    pub fn key_move(&mut self, dir: Direction) -> GameEvent {
+        self.cancel_auto_solve();
+
+        //the keys turn the row or column under the mouse
+        if self.board.loops() {
+            return match self.hover {
+                Some((x, y)) => {
+                    let (dx, dy) = dir.delta();
+                    if dx != 0 {
+                        self.input(Input::Shift(Line::Row(y), dx))
+                    } else {
+                        self.input(Input::Shift(Line::Column(x), dy))
+                    }
+                },
+                None => GameEvent::NoEvent
+            };
+        }
 
+        self.input(Input::Key(dir))
+    }
 
-        //prep the image
-        println!("Loading Image....");
-        let base_img = image::open(img_path).unwrap()
-            .resize_exact(width,height,image::FilterType::Triangle);
+    pub fn undo(&mut self) -> GameEvent {
+        self.cancel_auto_solve();
+        self.input(Input::Undo)
+    }
 
+    pub fn redo(&mut self) -> GameEvent {
+        self.cancel_auto_solve();
+        self.input(Input::Redo)
+    }
 
-        println!("Creating Tiles...");
-        let mut img_tiles: Vec<opengl_graphics::Texture> = Vec::new();
-        let mut cells: Vec<Cell> = Vec::new();
-        for i in 0..x_cells*y_cells {
-            let x = i % x_cells;
-            let y = i / x_cells;
+    pub fn rotate(&mut self, x_cell: u32, y_cell: u32) -> GameEvent {
+        self.cancel_auto_solve();
+        self.input(Input::Rotate((x_cell, y_cell)))
+    }
+
+    pub fn rotate_hovered(&mut self) -> GameEvent {
+        match self.hover {
+            Some((x, y)) => self.rotate(x, y),
+            None => GameEvent::NoEvent
+        }
+    }
+
+    //as big as the board can be in a w by h window with square
+    //cells, centered in the space above the HUD
+    pub fn area(&self, w: u32, h: u32) -> Area {
+        let space = h.saturating_sub(HUD_HEIGHT);
+        let (x_cells, y_cells) = (self.x_cells as u64, self.y_cells as u64);
+        let (area_w, area_h) = if w as u64 * y_cells <= space as u64 * x_cells {
+            (w, (w as u64 * y_cells / x_cells) as u32)
+        } else {
+            ((space as u64 * x_cells / y_cells) as u32, space)
+        };
+
+        Area {
+            x: (w - area_w) / 2,
+            y: (space - area_h) / 2,
+            w: area_w,
+            h: area_h
+        }
+    }
 
+    //the cell drawn at a point in a w by h window, which
+    //is off the board if the point is in the margins
+    pub fn cell_at(&self, raw_x: f64, raw_y: f64, w: u32, h: u32) -> (i32, i32) {
+        let area = self.area(w, h);
+        (part_at(raw_x - area.x as f64, self.x_cells, area.w),
+         part_at(raw_y - area.y as f64, self.y_cells, area.h))
+    }
 
-            cells.push(Cell {
-                x_pos: x,
-                y_pos: y,
-                content: Some(i)
-            });
+    //where a point is in the cell it's in, from -0.5 to 0.5
+    //across and down with (0, 0) in the middle
+    pub fn offset_in_cell(&self, raw_x: f64, raw_y: f64, w: u32, h: u32) -> (f64, f64) {
+        let area = self.area(w, h);
+        let (x, y) = self.cell_at(raw_x, raw_y, w, h);
+        let offset = |pos: f64, cell: i32, count: u32, size: u32| {
+            let cell = clamp(cell, 0, count as i32 - 1) as u32;
+            let (start, len) = span(cell, count, size);
+            (pos - start as f64) / len.max(1) as f64 - 0.5
+        };
+        (offset(raw_x - area.x as f64, x, self.x_cells, area.w),
+         offset(raw_y - area.y as f64, y, self.y_cells, area.h))
+    }
 
+    pub fn on_board(&self, cell: (i32, i32)) -> Option<(u32, u32)> {
+        let (x, y) = cell;
+        if x >= 0 && y >= 0 && (x as u32) < self.x_cells && (y as u32) < self.y_cells {
+            Some((x as u32, y as u32))
+        } else {
+            None
+        }
+    }
+
+    pub fn mouse_move(&mut self, raw_x: u32, raw_y: u32, w: u32, h: u32) {
+        let cell = self.cell_at(raw_x as f64, raw_y as f64, w, h);
+        self.hover = self.on_board(cell);
+    }
+
+    //cut the tiles again to fit a w by h window, if the board has
+    //changed size in it. when there isn't a pixel for every cell the
+    //old tiles are kept, or there are none until there's room
+    pub fn resize(&mut self, w: u32, h: u32) {
+        let area = self.area(w, h);
+        if (area.w, area.h) == self.tiles_size || area.w < self.x_cells || area.h < self.y_cells {
+            return;
+        }
 
-            let subimg = base_img.clone()
-                .crop(x*(width/x_cells),
-                y*(height/y_cells),
-                (width/x_cells),
-                (height/y_cells))
+        println!("Creating Tiles...");
+        let fitted = self.fit.fit(&self.picture, area.w, area.h);
+        let (x_cells, y_cells) = (self.x_cells, self.y_cells);
+
+        //each tile shows the part of the image where it ends up
+        let mut places = vec![(0, 0); x_cells as usize * y_cells as usize - self.board.blank_count()];
+        for (i, content) in self.board.goal_cells().iter().enumerate() {
+            if let Some(tile) = content {
+                places[*tile as usize] = (i as u32 % x_cells, i as u32 / x_cells);
+            }
+        }
+
+        let mut img_tiles: Vec<opengl_graphics::Texture> = Vec::new();
+        for (x, y) in places {
+            let (left, tile_w) = span(x, x_cells, area.w);
+            let (top, tile_h) = span(y, y_cells, area.h);
+            let subimg = fitted.clone()
+                .crop(left, top, tile_w, tile_h)
                 .to_rgba();
 
             let image: opengl_graphics::Texture = 
@@ -352,29 +1422,247 @@ impl Grid {
         }
         println!("Tiles Created...");
 
-        let (empty_x, empty_y) = (x_cells - 1, y_cells - 1);
+        self.img_tiles = img_tiles;
+        self.tiles_size = (area.w, area.h);
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.board.is_solved()
+    }
+
+    pub fn get_tile(&self, index: u32) -> &opengl_graphics::Texture {
+        self.img_tiles.get(index as usize).unwrap()
+    }
+
+
+    pub fn randomize(&mut self, mode: ShuffleMode) {
+        println!("Randomizing ({}, seed {})...", mode.name(), self.seed);
+        let mut rng = board::seeded_rng(self.seed);
+        self.board.shuffle_with(mode, &mut rng);
+        println!("Randomized...");
+    }
+
+    pub fn new (settings: &GameSettings,
+                seed: u64,
+                width: u32, 
+                height: u32) -> Result<Grid, LoadError> {
+
+        let board = Board::with_goal(settings.x_cells,
+                                     settings.y_cells,
+                                     settings.blanks,
+                                     settings.goal,
+                                     settings.variant);
+        let mut grid = Grid::with_board(board, width, height, &settings.img_path, settings.fit)?;
+        grid.seed = seed;
+        grid.shuffle = settings.shuffle;
+        grid.metric = settings.metric;
+
+        grid.randomize(settings.shuffle);
+        Ok(grid)
+    }
+
+    pub fn settings(&self) -> GameSettings {
+        GameSettings {
+            x_cells: self.x_cells,
+            y_cells: self.y_cells,
+            blanks: self.blank_count(),
+            goal: self.goal(),
+            variant: self.variant(),
+            shuffle: self.shuffle,
+            metric: self.metric,
+            img_path: self.img_path.clone(),
+            fit: self.fit
+        }
+    }
+
+    //see puzzle_text for the format
+    pub fn from_text(text: &str,
+                     goal: GoalLayout,
+                     variant: Variant,
+                     width: u32,
+                     height: u32,
+                     img_path: &str,
+                     fit: FitMode) -> Result<Grid, LoadError> {
+        let board = puzzle_text::parse(text, goal, variant)?;
+        let mut grid = Grid::with_board(board, width, height, img_path, fit)?;
+        //a hand picked board can't go on the leaderboard
+        grid.assisted = true;
+        Ok(grid)
+    }
+
+    pub fn to_text(&self) -> String {
+        puzzle_text::format(&self.board)
+    }
+
+    //pick a saved game back up, the clock stays
+    //stopped until the next move like a new game
+    pub fn from_saved(saved: SavedGame,
+                      width: u32,
+                      height: u32) -> Result<Grid, LoadError> {
+        let mut board = Board::from_cells_with_goal(saved.width,
+                                                    saved.height,
+                                                    saved.cells,
+                                                    saved.goal,
+                                                    saved.variant)?;
+        if !saved.turns.is_empty() {
+            board.set_turns(saved.turns)?;
+        }
+        let mut grid = Grid::with_board(board, width, height, &saved.img_path, saved.fit)?;
+        grid.move_counter = saved.move_counter;
+        grid.elapsed = saved.elapsed_ms as f64 / 1000.0;
+        grid.seed = saved.seed;
+        grid.shuffle = saved.shuffle;
+        grid.metric = saved.metric;
+        grid.assisted = saved.assisted;
+        grid.daily = saved.daily;
+        grid.move_budget = saved.move_budget;
+        grid.player_name = saved.player_name;
+        Ok(grid)
+    }
+
+    pub fn to_saved(&self) -> SavedGame {
+        SavedGame {
+            width: self.x_cells,
+            height: self.y_cells,
+            cells: self.board.cells().to_vec(),
+            turns: self.board.turns().to_vec(),
+            goal: self.board.goal(),
+            variant: self.board.variant(),
+            move_counter: self.move_counter,
+            elapsed_ms: self.elapsed_ms(),
+            seed: self.seed,
+            shuffle: self.shuffle,
+            metric: self.metric,
+            assisted: self.assisted,
+            daily: self.daily,
+            move_budget: self.move_budget,
+            player_name: self.player_name.clone(),
+            img_path: self.img_path.clone(),
+            fit: self.fit
+        }
+    }
+
+    fn with_board(board: Board,
+                  width: u32,
+                  height: u32,
+                  img_path: &str,
+                  fit: FitMode) -> Result<Grid, LoadError> {
+        let (x_cells, y_cells) = (board.width, board.height);
 
-        cells.get_mut(
-            Grid::get_grid_index(empty_x, 
-                                 empty_y, 
-                                 x_cells) 
-            as usize)
-            .unwrap()
-            .content = None;
+        //prep the image
+        println!("Loading Image....");
+        let picture = image::open(img_path)
+            .map_err(|e| LoadError::Image(img_path.to_string(), e))?;
 
         let mut grid = Grid {
             x_cells: x_cells,
             y_cells: y_cells,
-            empty_x: empty_x,
-            empty_y: empty_y,
-            img_tiles: img_tiles,
-            cells: cells,
-            move_counter: 0
+            board: board,
+            picture: picture,
+            img_tiles: Vec::new(),
+            tiles_size: (0, 0),
+            move_counter: 0,
+            metric: MoveMetric::Mtm,
+            key_mode: KeyMode::Tile,
+            shuffle: ShuffleMode::Uniform,
+            img_path: img_path.to_string(),
+            fit: fit,
+            player_name: String::new(),
+            elapsed: 0.0,
+            timer_running: false,
+            seed: 0,
+            history: Vec::new(),
+            redo_stack: Vec::new(),
+            hover: None,
+            drag_start: None,
+            speed: SlideSpeed::Normal,
+            animation: None,
+            queue: VecDeque::new(),
+            assisted: false,
+            daily: None,
+            solver_rx: None,
+            solver_cancel: Arc::new(AtomicBool::new(false)),
+            hint_pending: false,
+            hint: None,
+            auto_moves: Vec::new(),
+            auto_timer: 0.0,
+            move_limit: None,
+            move_budget: None,
+            budget_rx: None,
+            budget_cancel: Arc::new(AtomicBool::new(false))
         };
 
-        grid.randomize(x_cells * y_cells * 10);
-        grid
+        grid.resize(width, height);
+        Ok(grid)
     }
 }
 
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_cover_every_pixel_once() {
+        for size in 0..60 {
+            for count in 1..12 {
+                let mut next = 0;
+                for i in 0..count {
+                    let (start, len) = span(i, count, size);
+                    assert_eq!(start, next, "part {} of {} in {}", i, count, size);
+                    //no part is more than a pixel bigger than another
+                    assert!(len == size / count || len == size / count + 1);
+                    next = start + len;
+                }
+                assert_eq!(next, size);
+            }
+        }
+    }
+
+    #[test]
+    fn points_land_in_their_span() {
+        for &(count, size) in &[(3, 100), (4, 4), (5, 17), (7, 300)] {
+            for pos in 0..size {
+                let part = part_at(pos as f64 + 0.5, count, size);
+                let (start, len) = span(part as u32, count, size);
+                assert!(start <= pos && pos < start + len);
+            }
+            //off either end still counts, so drags can go past the board
+            assert_eq!(part_at(-1.0, count, size), -1);
+            assert_eq!(part_at(size as f64, count, size), count as i32);
+        }
+    }
+
+    #[test]
+    fn wide_pictures_are_placed_by_fit() {
+        //a 2:1 picture on a square board
+        assert_eq!(FitMode::Letterbox.placement(400, 200, 100, 100), ((100, 50), (0, 25)));
+        assert_eq!(FitMode::Crop.placement(400, 200, 100, 100), ((200, 100), (50, 0)));
+        assert_eq!(FitMode::Stretch.placement(400, 200, 100, 100), ((100, 100), (0, 0)));
+    }
+
+    #[test]
+    fn tall_pictures_are_placed_by_fit() {
+        //a 1:3 picture on a 3:2 board
+        assert_eq!(FitMode::Letterbox.placement(100, 300, 150, 100), ((33, 100), (58, 0)));
+        assert_eq!(FitMode::Crop.placement(100, 300, 150, 100), ((150, 450), (0, 175)));
+        assert_eq!(FitMode::Stretch.placement(100, 300, 150, 100), ((150, 100), (0, 0)));
+    }
+
+    #[test]
+    fn placements_cover_or_fit_inside_the_board() {
+        for &(img_w, img_h) in &[(640, 480), (481, 640), (1, 1000), (999, 7), (300, 300)] {
+            for &(width, height) in &[(100, 100), (320, 95), (33, 400)] {
+                let ((w, h), (x, y)) = FitMode::Crop.placement(img_w, img_h, width, height);
+                assert!(w >= width && h >= height);
+                assert!(x + width <= w && y + height <= h);
+                assert!(w == width || h == height);
+
+                let ((w, h), (x, y)) = FitMode::Letterbox.placement(img_w, img_h, width, height);
+                assert!(w >= 1 && h >= 1);
+                assert!(x + w <= width && y + h <= height);
+                assert!(w == width || h == height);
+            }
+        }
+    }
+}
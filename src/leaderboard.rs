@@ -0,0 +1,219 @@
+use piston::input::*;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use graphics::*;
+use title_page::{Button, ButtonPos, ButtonState};
+use std::path::Path;
+use score::{self, ScoreBook, ScoreEntry, ScoreKey, SortBy};
+use date;
+use board::{GoalLayout, Variant};
+
+
+//how many results are listed for each set of settings
+const TOP_N: usize = 8;
+
+
+pub enum LeaderboardEvent {
+    BackClick,
+    NoEvent
+}
+
+
+pub struct Leaderboard {
+    //every set of settings with scores, each one its own list
+    keys: Vec<ScoreKey>,
+    key_index: usize,
+    sort: SortBy,
+    entries: Vec<ScoreEntry>,
+    prev_btn: Button,
+    next_btn: Button,
+    sort_btn: Button,
+    back_btn: Button
+}
+
+impl Leaderboard {
+
+    fn render_text(s: &str,
+                   glyph: &mut GlyphCache,
+                   t: math::Matrix2d,
+                   font_size: u32,
+                   color: [f32; 4],
+                   gl: &mut GlGraphics){
+        text::Text::new_color(color, font_size)
+            .draw(s,
+                  glyph,
+                  &DrawState::default(),
+                  t,
+                  gl)
+            .unwrap();
+    }
+
+    pub fn render(&self,
+                  gl: &mut GlGraphics,
+                  t: &math::Matrix2d,
+                  glyph: &mut GlyphCache,
+                  args: &RenderArgs) {
+        let bg_col: [f32; 4] = [1.0,1.0,1.0,1.0];
+        let black: [f32; 4] = [0.0,0.0,0.0,1.0];
+        let grey: [f32; 4] = [0.4,0.4,0.4,1.0];
+        clear(bg_col, gl);
+
+        self.prev_btn.render(gl,t,glyph,args);
+        self.next_btn.render(gl,t,glyph,args);
+        self.sort_btn.render(gl,t,glyph,args);
+        self.back_btn.render(gl,t,glyph,args);
+
+        let key = self.keys.get(self.key_index);
+        let header = match key {
+            Some(key) => format!("Leaderboard {}x{} by {}", key.width, key.height, self.sort.name()),
+            None => "Leaderboard".to_string()
+        };
+        Leaderboard::render_text(&header, glyph, t.trans(10.0, 35.0), 24, black, gl);
+        if let Some(key) = key {
+            Leaderboard::render_text(&Leaderboard::describe(key),
+                                     glyph,
+                                     t.trans(10.0, 57.0),
+                                     14,
+                                     grey,
+                                     gl);
+        }
+
+        if self.entries.is_empty() {
+            Leaderboard::render_text("No scores yet",
+                                     glyph,
+                                     t.trans(10.0, 85.0),
+                                     18,
+                                     grey,
+                                     gl);
+        }
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let y = 85.0 + 40.0 * i as f64;
+            let name = if entry.name.is_empty() { "-" } else { entry.name.as_str() };
+
+            let line = format!("{}. {}  {} moves  {}",
+                               i + 1,
+                               name,
+                               entry.score.moves,
+                               score::format_time(entry.score.time_ms));
+            Leaderboard::render_text(&line, glyph, t.trans(10.0, y), 16, black, gl);
+
+            let date = if entry.date == 0 {
+                "-".to_string()
+            } else {
+                date::format_date(entry.date)
+            };
+            let line = format!("{}  Seed {}", date, entry.score.seed);
+            Leaderboard::render_text(&line, glyph, t.trans(25.0, y + 17.0), 14, grey, gl);
+        }
+    }
+
+    //the settings a list is for, the unusual ones only when they're set
+    fn describe(key: &ScoreKey) -> String {
+        let mut parts = vec![key.shuffle.name().to_string(), key.metric.name().to_string()];
+        if key.blanks > 1 {
+            parts.push(format!("{} blanks", key.blanks));
+        }
+        if key.goal != GoalLayout::RowMajor {
+            parts.push(key.goal.name().to_string());
+        }
+        if key.variant != Variant::Slide {
+            parts.push(key.variant.name().to_string());
+        }
+        if let Some(file) = Path::new(&key.image).file_name() {
+            parts.push(file.to_string_lossy().into_owned());
+        }
+        parts.join(", ")
+    }
+
+    fn refresh(&mut self, scores: &ScoreBook) {
+        self.entries = match self.keys.get(self.key_index) {
+            Some(key) => scores.top(key, self.sort, TOP_N),
+            None => Vec::new()
+        };
+    }
+
+    pub fn click(&mut self,
+                 raw_x: u32,
+                 raw_y: u32,
+                 w: u32,
+                 h: u32,
+                 scores: &ScoreBook) -> LeaderboardEvent {
+        if self.back_btn.in_bound(raw_x, raw_y, w, h) {
+            return LeaderboardEvent::BackClick;
+        }
+
+        if !self.keys.is_empty() {
+            if self.prev_btn.in_bound(raw_x, raw_y, w, h) {
+                self.key_index = (self.key_index + self.keys.len() - 1) % self.keys.len();
+            }
+
+            if self.next_btn.in_bound(raw_x, raw_y, w, h) {
+                self.key_index = (self.key_index + 1) % self.keys.len();
+            }
+        }
+
+        if self.sort_btn.in_bound(raw_x, raw_y, w, h) {
+            self.sort = self.sort.next();
+            self.sort_btn.label = format!("By {}", self.sort.next().name());
+        }
+
+        self.refresh(scores);
+        LeaderboardEvent::NoEvent
+    }
+
+    pub fn mouse_move(&mut self, raw_x: u32, raw_y: u32, w: u32, h: u32) {
+        self.prev_btn.mouse_move(raw_x, raw_y, w, h);
+        self.next_btn.mouse_move(raw_x, raw_y, w, h);
+        self.sort_btn.mouse_move(raw_x, raw_y, w, h);
+        self.back_btn.mouse_move(raw_x, raw_y, w, h);
+    }
+
+    //starts on the settings picked on the title if they have scores,
+    //otherwise on the first list for the same board size
+    pub fn new(scores: &ScoreBook, key: &ScoreKey) -> Self {
+        let keys = scores.keys();
+        let key_index = keys
+            .iter()
+            .position(|other| other == key)
+            .or_else(|| keys.iter().position(|other| (other.width, other.height) == (key.width, key.height)))
+            .unwrap_or(0);
+
+        let mut leaderboard = Leaderboard {
+            keys: keys,
+            key_index: key_index,
+            sort: SortBy::Moves,
+            entries: Vec::new(),
+            prev_btn: Button {
+                pos: ButtonPos::CenteredOffset((-150, 445)),
+                w: 40,
+                h: 40,
+                label: "<".to_string(),
+                state: ButtonState::Normal
+            },
+            next_btn: Button {
+                pos: ButtonPos::CenteredOffset((-100, 445)),
+                w: 40,
+                h: 40,
+                label: ">".to_string(),
+                state: ButtonState::Normal
+            },
+            sort_btn: Button {
+                pos: ButtonPos::CenteredOffset((10, 445)),
+                w: 100,
+                h: 40,
+                label: "By Time".to_string(),
+                state: ButtonState::Normal
+            },
+            back_btn: Button {
+                pos: ButtonPos::CenteredOffset((140, 445)),
+                w: 80,
+                h: 40,
+                label: "Back".to_string(),
+                state: ButtonState::Normal
+            }
+        };
+
+        leaderboard.refresh(scores);
+        leaderboard
+    }
+}
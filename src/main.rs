@@ -5,7 +5,16 @@ extern crate opengl_graphics;
 extern crate tinyfiledialogs;
 
 mod app;
+mod board;
+mod daily;
+mod date;
 mod grid;
+mod leaderboard;
+mod pattern_db;
+mod puzzle_text;
+mod save;
+mod score;
+mod solver;
 mod title_page;
 
 use piston::window::WindowSettings;
@@ -20,7 +29,7 @@ use title_page::{Title, TitleEvent};
 fn main() {
 
     let opengl = OpenGL::V3_2;
-    let (mut window_width,mut window_height) = (400,400);
+    let (mut window_width,mut window_height) = (400,500);
 
     let mut window: Window = WindowSettings::new(
         "Slide Puzzle",
@@ -64,6 +73,7 @@ fn main() {
         e.resize(|w, h|{
             window_width = w;
             window_height = h;
+            app.resize(w, h);
         });
 
         if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
@@ -74,5 +84,29 @@ fn main() {
                       window_height);
 
         }
+
+        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
+            app.release(mx as f32,
+                        my as f32,
+                        window_width,
+                        window_height);
+        }
+
+        if let Some(Button::Mouse(MouseButton::Right)) = e.press_args() {
+            app.right_click(mx as f32,
+                            my as f32,
+                            window_width,
+                            window_height);
+        }
+
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            app.key_press(key);
+        }
+
+        if let Some(Button::Keyboard(key)) = e.release_args() {
+            app.key_release(key);
+        }
     }
+
+    app.exit();
 }
@@ -0,0 +1,437 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+
+const CACHE_DIR: &str = "./cache";
+const MAGIC: &[u8; 4] = b"SPDB";
+const VERSION: u8 = 1;
+const UNSEEN: u8 = 255;
+
+
+//n / d rounded up, for bitsets here and picture sizes in grid
+pub fn div_ceil(n: u64, d: u64) -> u64 {
+    n / d + if n % d == 0 { 0 } else { 1 }
+}
+
+
+//disjoint additive pattern database. each group of tiles gets a
+//table of the fewest moves of *its own* tiles needed to get them
+//home, so the costs of all groups can be added together
+pub struct PatternDb {
+    pub width: u32,
+    pub height: u32,
+    groups: Vec<Vec<u32>>,
+    tables: Vec<Vec<u8>>,
+    //group each tile belongs to, indexed by tile
+    tile_groups: Vec<Option<usize>>
+}
+
+impl PatternDb {
+
+    //the tile groups used for the board sizes we keep databases for
+    pub fn partition(width: u32, height: u32) -> Option<Vec<Vec<u32>>> {
+        match (width, height) {
+            (4, 4) => Some(vec![
+                vec![0, 4, 5, 8, 9, 12],
+                vec![6, 7, 10, 11, 13, 14],
+                vec![1, 2, 3]
+            ]),
+            (5, 5) => Some(vec![
+                vec![0, 1, 5, 6, 10],
+                vec![2, 3, 4, 7, 8],
+                vec![11, 15, 16, 20, 21],
+                vec![9, 13, 14, 18, 19],
+                vec![12, 17, 22, 23]
+            ]),
+            _ => None
+        }
+    }
+
+
+    //number of ways to place k tiles on n cells
+    fn table_size(cells: usize, k: usize) -> usize {
+        (0..k).fold(1, |acc, i| acc * (cells - i))
+    }
+
+
+    //index of a placement of distinct cells, in the mixed radix
+    //n, n-1, n-2... after removing the cells already used
+    fn rank(cells: usize, placement: &[usize]) -> usize {
+        let mut index = 0;
+        for i in 0..placement.len() {
+            let smaller = placement[..i]
+                .iter()
+                .filter(|&&p| p < placement[i])
+                .count();
+            index = index * (cells - i) + placement[i] - smaller;
+        }
+        index
+    }
+
+    fn unrank(cells: usize, mut index: usize, placement: &mut [usize]) {
+        let k = placement.len();
+        for i in (0..k).rev() {
+            let radix = cells - i;
+            placement[i] = index % radix;
+            index /= radix;
+        }
+
+        //boards are never bigger than 64 cells, so the used
+        //cells fit in a mask
+        let mut used: u64 = 0;
+        for i in 0..k {
+            let mut skip = placement[i];
+            let mut pos = 0;
+            loop {
+                if used & (1 << pos) == 0 {
+                    if skip == 0 {
+                        break;
+                    }
+                    skip -= 1;
+                }
+                pos += 1;
+            }
+            used |= 1 << pos;
+            placement[i] = pos;
+        }
+    }
+
+
+    //0-1 breadth first search back from the goal over the group's
+    //tiles plus the blank. moving the blank onto a cell without one of
+    //the group's tiles is free, so the cost only counts the group's tiles
+    fn build_table(width: usize, height: usize, group: &[u32]) -> Vec<u8> {
+        let cells = width * height;
+        let k = group.len();
+        let size = PatternDb::table_size(cells, k);
+
+        let mut table = vec![UNSEEN; size];
+        let words = div_ceil((size * cells) as u64, 64) as usize;
+        let mut visited = vec![0u64; words];
+        let mut queued = vec![0u64; words];
+
+        fn is_set(bits: &[u64], i: usize) -> bool {
+            bits[i / 64] & (1 << (i % 64)) != 0
+        }
+        fn set(bits: &mut [u64], i: usize) {
+            bits[i / 64] |= 1 << (i % 64);
+        }
+
+        let goal: Vec<usize> = group.iter().map(|&t| t as usize).collect();
+        let start = PatternDb::rank(cells, &goal) * cells + cells - 1;
+
+        let mut current: Vec<usize> = vec![start];
+        let mut cost: u8 = 0;
+        let mut placement = vec![0; k];
+
+        while !current.is_empty() {
+            let mut next: Vec<usize> = Vec::new();
+            let mut stack: Vec<usize> = Vec::new();
+
+            for state in current.drain(..) {
+                if !is_set(&visited, state) {
+                    set(&mut visited, state);
+                    stack.push(state);
+                }
+            }
+
+            while let Some(state) = stack.pop() {
+                let (index, blank) = (state / cells, state % cells);
+                if table[index] == UNSEEN {
+                    table[index] = cost;
+                }
+
+                PatternDb::unrank(cells, index, &mut placement);
+                let (bx, by) = (blank % width, blank / width);
+
+                let neighbours = [
+                    if by > 0 { Some(blank - width) } else { None },
+                    if by < height - 1 { Some(blank + width) } else { None },
+                    if bx > 0 { Some(blank - 1) } else { None },
+                    if bx < width - 1 { Some(blank + 1) } else { None }
+                ];
+
+                for &cell in neighbours.iter().flatten() {
+                    match placement.iter().position(|&p| p == cell) {
+                        Some(tile) => {
+                            placement[tile] = blank;
+                            let moved = PatternDb::rank(cells, &placement) * cells + cell;
+                            placement[tile] = cell;
+                            if !is_set(&visited, moved) && !is_set(&queued, moved) {
+                                set(&mut queued, moved);
+                                next.push(moved);
+                            }
+                        },
+                        None => {
+                            let moved = index * cells + cell;
+                            if !is_set(&visited, moved) {
+                                set(&mut visited, moved);
+                                stack.push(moved);
+                            }
+                        }
+                    }
+                }
+            }
+
+            current = next;
+            cost += 1;
+        }
+
+        table
+    }
+
+
+    pub fn build(width: u32, height: u32, groups: Vec<Vec<u32>>) -> PatternDb {
+        let tables = groups
+            .iter()
+            .map(|group| PatternDb::build_table(width as usize, height as usize, group))
+            .collect();
+
+        PatternDb::from_parts(width, height, groups, tables)
+    }
+
+    fn from_parts(width: u32,
+                  height: u32,
+                  groups: Vec<Vec<u32>>,
+                  tables: Vec<Vec<u8>>) -> PatternDb {
+        let mut tile_groups = vec![None; (width * height) as usize];
+        for (i, group) in groups.iter().enumerate() {
+            for &tile in group {
+                tile_groups[tile as usize] = Some(i);
+            }
+        }
+
+        PatternDb {
+            width: width,
+            height: height,
+            groups: groups,
+            tables: tables,
+            tile_groups: tile_groups
+        }
+    }
+
+
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn group_of(&self, tile: u32) -> Option<usize> {
+        match self.tile_groups.get(tile as usize) {
+            Some(group) => *group,
+            None => None
+        }
+    }
+
+    //cost of a group given the position of every tile, indexed by tile
+    pub fn lookup(&self, group: usize, positions: &[usize]) -> u32 {
+        let cells = (self.width * self.height) as usize;
+        let tiles = &self.groups[group];
+
+        //same as rank, without collecting the placement first
+        let mut index = 0;
+        for i in 0..tiles.len() {
+            let pos = positions[tiles[i] as usize];
+            let smaller = tiles[..i]
+                .iter()
+                .filter(|&&t| positions[t as usize] < pos)
+                .count();
+            index = index * (cells - i) + pos - smaller;
+        }
+
+        self.tables[group][index] as u32
+    }
+
+
+    fn cache_path(width: u32, height: u32) -> PathBuf {
+        PathBuf::from(CACHE_DIR).join(format!("pdb_{}x{}.bin", width, height))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(CACHE_DIR)?;
+        let mut file = File::create(PatternDb::cache_path(self.width, self.height))?;
+
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION,
+                         self.width as u8,
+                         self.height as u8,
+                         self.groups.len() as u8])?;
+
+        for (group, table) in self.groups.iter().zip(&self.tables) {
+            file.write_all(&[group.len() as u8])?;
+            let tiles: Vec<u8> = group.iter().map(|&t| t as u8).collect();
+            file.write_all(&tiles)?;
+            file.write_all(table)?;
+        }
+
+        Ok(())
+    }
+
+    //only gives back the cached database if it was built
+    //with the same groups we would build it with now
+    pub fn load(width: u32, height: u32, groups: &[Vec<u32>]) -> io::Result<PatternDb> {
+        let bad_data = || io::Error::new(io::ErrorKind::InvalidData,
+                                         "pattern database cache doesn't match");
+
+        let mut bytes = Vec::new();
+        File::open(PatternDb::cache_path(width, height))?.read_to_end(&mut bytes)?;
+
+        let header = [VERSION, width as u8, height as u8, groups.len() as u8];
+        if bytes.len() < 8 || &bytes[..4] != MAGIC || bytes[4..8] != header {
+            return Err(bad_data());
+        }
+
+        let cells = (width * height) as usize;
+        let mut offset = 8;
+        let mut tables = Vec::new();
+        for group in groups {
+            let k = group.len();
+            let size = PatternDb::table_size(cells, k);
+            if bytes.len() < offset + 1 + k + size || bytes[offset] as usize != k {
+                return Err(bad_data());
+            }
+            offset += 1;
+
+            let tiles: Vec<u32> = bytes[offset..offset + k].iter().map(|&t| t as u32).collect();
+            if &tiles != group {
+                return Err(bad_data());
+            }
+            offset += k;
+
+            tables.push(bytes[offset..offset + size].to_vec());
+            offset += size;
+        }
+
+        Ok(PatternDb::from_parts(width, height, groups.to_vec(), tables))
+    }
+}
+
+
+//the databases that have finished loading, shared with the game
+#[derive(Clone)]
+pub struct PatternDbs {
+    dbs: Arc<Mutex<Vec<Arc<PatternDb>>>>
+}
+
+impl PatternDbs {
+
+    //load (or build) every database on another thread,
+    //smallest first so it's available as soon as possible
+    pub fn load_in_background() -> PatternDbs {
+        let dbs = PatternDbs {
+            dbs: Arc::new(Mutex::new(Vec::new()))
+        };
+
+        let shared = dbs.clone();
+        thread::spawn(move || {
+            for &(w, h) in &[(4, 4), (5, 5)] {
+                if let Some(db) = PatternDbs::load_or_build(w, h) {
+                    shared.dbs.lock().unwrap().push(Arc::new(db));
+                }
+            }
+        });
+
+        dbs
+    }
+
+    //the cached database if it's still good, otherwise a new one
+    //that's cached for next time. this is the game starting up,
+    //so it says what it's doing
+    fn load_or_build(width: u32, height: u32) -> Option<PatternDb> {
+        let groups = PatternDb::partition(width, height)?;
+
+        match PatternDb::load(width, height, &groups) {
+            Ok(db) => {
+                println!("Loaded {}x{} pattern database", width, height);
+                Some(db)
+            },
+            Err(e) => {
+                println!("Couldn't load {}x{} pattern database ({}), building it...",
+                         width, height, e);
+                let db = PatternDb::build(width, height, groups);
+                if let Err(e) = db.save() {
+                    println!("Couldn't save pattern database: {}", e);
+                }
+                Some(db)
+            }
+        }
+    }
+
+    pub fn get(&self, width: u32, height: u32) -> Option<Arc<PatternDb>> {
+        self.dbs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|db| db.width == width && db.height == height)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use board::{self, Board, ShuffleMode};
+    use solver;
+
+    //a database small enough to build in a test
+    fn small_db() -> PatternDb {
+        PatternDb::build(3, 3, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]])
+    }
+
+    //the cell of every tile, with the blank last
+    fn positions(board: &Board) -> Vec<usize> {
+        let mut positions = vec![0; board.cells().len()];
+        let blank = positions.len() - 1;
+        for (pos, content) in board.cells().iter().enumerate() {
+            positions[content.map(|tile| tile as usize).unwrap_or(blank)] = pos;
+        }
+        positions
+    }
+
+    #[test]
+    fn rank_and_unrank_agree() {
+        let cells = 9;
+        let mut placement = vec![0; 3];
+        for index in 0..PatternDb::table_size(cells, 3) {
+            PatternDb::unrank(cells, index, &mut placement);
+            assert_eq!(PatternDb::rank(cells, &placement), index);
+        }
+    }
+
+    #[test]
+    fn goal_costs_nothing() {
+        let db = small_db();
+        let goal = positions(&Board::new(3, 3));
+        for group in 0..db.group_count() {
+            assert_eq!(db.lookup(group, &goal), 0);
+        }
+    }
+
+    #[test]
+    fn estimate_never_overshoots() {
+        let db = small_db();
+        for (board, &distance) in &solver::tests::distances(&Board::new(3, 3)) {
+            let positions = positions(board);
+            let estimate: u32 = (0..db.group_count())
+                .map(|group| db.lookup(group, &positions))
+                .sum();
+            assert!(estimate <= distance, "{} > {} for {:?}", estimate, distance, board);
+        }
+    }
+
+    #[test]
+    fn solutions_are_as_short_as_without_it() {
+        let db = small_db();
+        let mut rng = board::seeded_rng(5);
+        for _ in 0..20 {
+            let mut board = Board::new(3, 3);
+            board.shuffle_with(ShuffleMode::Uniform, &mut rng);
+            let plain = solver::solve(&board).unwrap();
+            let with_db = solver::solve_with(&board, Some(&db), &AtomicBool::new(false)).unwrap();
+            assert_eq!(plain.len(), with_db.len());
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::error::Error;
+use std::fmt;
+use board::{Board, BoardError, GoalLayout, Variant};
+
+
+//boards as plain text, the way they're usually written down:
+//
+//  4 4
+//   1  2  3  4
+//   5  6  7  8
+//   9 10 11 12
+//  13 14 15  0
+//
+//a width and height header, then one line per row with tiles
+//numbered from 1 and 0 for the blanks. loopover boards have no
+//blank, so their tiles go up to width * height. on rotate boards a
+//turned tile has its quarter turns clockwise after a colon, like 5:3.
+//blank lines and lines starting with # are skipped
+
+
+//far bigger than anyone would play, but keeps a typo in the header
+//from asking for billions of cells
+pub const MAX_CELLS: u32 = 10_000;
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl ParseError {
+    fn new(line: usize, column: usize, message: String) -> ParseError {
+        ParseError {
+            line: line,
+            column: column,
+            message: message
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        "couldn't read board"
+    }
+}
+
+
+//the words on a line with their 1 based columns
+fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (i, c) in line.char_indices() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                tokens.push((s, &line[s..i]));
+                start = None;
+            },
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, &line[s..]));
+    }
+
+    tokens
+        .into_iter()
+        .map(|(i, token)| (line[..i].chars().count() + 1, token))
+        .collect()
+}
+
+
+//tiles are numbered by the goal layout they're meant to be solved to
+pub fn parse(text: &str, goal: GoalLayout, variant: Variant) -> Result<Board, ParseError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        });
+
+    //the header, either "4 4" or "4x4"
+    let (header_line, header) = match lines.next() {
+        Some(line) => line,
+        None => return Err(ParseError::new(1, 1, "expected a width and height".to_string()))
+    };
+    let header = header.replace(|c| c == 'x' || c == 'X', " ");
+    let dims = tokens(&header);
+    if dims.len() != 2 {
+        let column = dims.get(2).map(|&(column, _)| column).unwrap_or(1);
+        return Err(ParseError::new(header_line,
+                                   column,
+                                   "expected a width and height".to_string()));
+    }
+
+    let mut size = [0u32; 2];
+    for (i, &(column, token)) in dims.iter().enumerate() {
+        size[i] = match token.parse::<u32>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(ParseError::new(header_line,
+                                            column,
+                                            format!("'{}' isn't a board size", token)))
+        };
+    }
+    let (width, height) = (size[0], size[1]);
+    let tile_count = match width.checked_mul(height) {
+        Some(count) if count <= MAX_CELLS => count,
+        _ => return Err(ParseError::new(header_line,
+                                        dims[1].0,
+                                        format!("a {}x{} board is too big, it can have at most {} cells",
+                                                width,
+                                                height,
+                                                MAX_CELLS)))
+    };
+    //the highest number a tile can have
+    let top_tile = if variant == Variant::Loopover { tile_count } else { tile_count - 1 };
+
+    let mut cells: Vec<Option<u32>> = Vec::with_capacity(tile_count as usize);
+    let mut turns: Vec<u8> = Vec::with_capacity(tile_count as usize);
+    let mut seen = vec![false; top_tile as usize + 1];
+    //where each cell was written, for errors found once they're all read
+    let mut places: Vec<(usize, usize)> = Vec::with_capacity(tile_count as usize);
+    let mut last_line = header_line;
+
+    for row in 0..height {
+        let (line_no, line) = match lines.next() {
+            Some(line) => line,
+            None => return Err(ParseError::new(last_line + 1,
+                                               1,
+                                               format!("expected {} rows but found {}",
+                                                       height,
+                                                       row)))
+        };
+        last_line = line_no;
+
+        let row_tokens = tokens(line);
+        if row_tokens.len() as u32 != width {
+            let column = row_tokens
+                .get(width as usize)
+                .map(|&(column, _)| column)
+                .unwrap_or(line.chars().count() + 1);
+            return Err(ParseError::new(line_no,
+                                       column,
+                                       format!("expected {} tiles but found {}",
+                                               width,
+                                               row_tokens.len())));
+        }
+
+        for (column, token) in row_tokens {
+            let (token, turn) = match token.find(':') {
+                Some(i) => (&token[..i], Some(&token[i + 1..])),
+                None => (token, None)
+            };
+            let turn = match turn {
+                None => 0,
+                Some(_) if variant != Variant::Rotate => {
+                    return Err(ParseError::new(line_no,
+                                               column,
+                                               "tiles only turn on rotate boards".to_string()))
+                },
+                Some(turn) => match turn.parse::<u8>() {
+                    Ok(turn) if turn < 4 => turn,
+                    _ => return Err(ParseError::new(line_no,
+                                                    column + token.chars().count() + 1,
+                                                    format!("'{}' isn't a number of quarter turns", turn)))
+                }
+            };
+            let tile = match token.parse::<u32>() {
+                Ok(tile) => tile,
+                Err(_) => return Err(ParseError::new(line_no,
+                                                     column,
+                                                     format!("'{}' isn't a tile number", token)))
+            };
+            if tile > top_tile {
+                return Err(ParseError::new(line_no,
+                                           column,
+                                           format!("tile {} doesn't fit on a {}x{} board",
+                                                   tile,
+                                                   width,
+                                                   height)));
+            }
+            if tile != 0 && seen[tile as usize] {
+                return Err(ParseError::new(line_no,
+                                           column,
+                                           format!("tile {} appears more than once", tile)));
+            }
+            seen[tile as usize] = true;
+            if tile == 0 && turn != 0 {
+                return Err(ParseError::new(line_no,
+                                           column,
+                                           "the blank can't be turned".to_string()));
+            }
+
+            places.push((line_no, column));
+            cells.push(if tile == 0 { None } else { Some(tile - 1) });
+            turns.push(turn);
+        }
+    }
+
+    if let Some((line_no, line)) = lines.next() {
+        let column = line.chars().position(|c| !c.is_whitespace()).unwrap_or(0) + 1;
+        return Err(ParseError::new(line_no,
+                                   column,
+                                   format!("expected {} rows but found more", height)));
+    }
+
+    //tiles are only known to fit once the blanks are counted
+    let place_of = |tile: u32| {
+        cells.iter()
+            .position(|&c| c == Some(tile))
+            .map(|i| places[i])
+            .unwrap_or((header_line, 1))
+    };
+    let blanks = cells.iter().filter(|c| c.is_none()).count();
+    let mut board = Board::from_cells_with_goal(width, height, cells.clone(), goal, variant)
+        .map_err(|e| match e {
+            BoardError::InvalidTile(tile) => {
+                let (line, column) = place_of(tile);
+                ParseError::new(line,
+                                column,
+                                format!("tile {} doesn't fit on a {}x{} board with {} blanks",
+                                        tile + 1,
+                                        width,
+                                        height,
+                                        blanks))
+            },
+            _ => ParseError::new(header_line, 1, e.to_string())
+        })?;
+    board.set_turns(turns).map_err(|e| ParseError::new(header_line, 1, e.to_string()))?;
+    Ok(board)
+}
+
+
+pub fn format(board: &Board) -> String {
+    let tokens: Vec<String> = (0..board.height)
+        .flat_map(|y| (0..board.width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let tile = match board.get(x, y) {
+                Some(tile) => tile + 1,
+                None => 0
+            };
+            match board.turn(x, y) {
+                0 => tile.to_string(),
+                turn => format!("{}:{}", tile, turn)
+            }
+        })
+        .collect();
+    let width = tokens.iter().map(|token| token.len()).max().unwrap_or(1);
+    let mut text = format!("{} {}\n", board.width, board.height);
+
+    for row in tokens.chunks(board.width as usize) {
+        let row: Vec<String> = row
+            .iter()
+            .map(|token| format!("{:>width$}", token, width = width))
+            .collect();
+        text.push_str(&row.join(" "));
+        text.push('\n');
+    }
+
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::{self, ShuffleMode};
+
+    fn error_at(text: &str) -> (usize, usize) {
+        let e = parse(text, GoalLayout::RowMajor, Variant::Slide).unwrap_err();
+        (e.line, e.column)
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let goals = [GoalLayout::RowMajor, GoalLayout::Spiral, GoalLayout::ColumnMajor];
+        for (seed, &goal) in goals.iter().enumerate() {
+            for &(w, h, blanks) in &[(3, 3, 1), (4, 4, 1), (5, 3, 2), (4, 5, 1)] {
+                let mut board = Board::with_goal(w, h, blanks, goal, Variant::Slide);
+                board.shuffle_with(ShuffleMode::Uniform, &mut board::seeded_rng(seed as u64));
+                let text = format(&board);
+                assert_eq!(parse(&text, goal, Variant::Slide), Ok(board), "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_loopover_boards() {
+        let mut board = Board::with_goal(3, 3, 0, GoalLayout::RowMajor, Variant::Loopover);
+        board.shuffle_with(ShuffleMode::Scramble, &mut board::seeded_rng(3));
+        let text = format(&board);
+        assert_eq!(parse(&text, GoalLayout::RowMajor, Variant::Loopover), Ok(board));
+    }
+
+    #[test]
+    fn keeps_the_turns_of_rotate_boards() {
+        let mut board = Board::with_goal(3, 3, 1, GoalLayout::RowMajor, Variant::Rotate);
+        board.shuffle_with(ShuffleMode::Uniform, &mut board::seeded_rng(8));
+        assert!(board.turns().iter().any(|&turn| turn != 0));
+        let text = format(&board);
+        assert!(text.contains(':'), "{}", text);
+        assert_eq!(parse(&text, GoalLayout::RowMajor, Variant::Rotate), Ok(board));
+    }
+
+    #[test]
+    fn only_tiles_on_rotate_boards_turn() {
+        let text = "2 2\n1 2:1\n3 0\n";
+        let board = parse(text, GoalLayout::RowMajor, Variant::Rotate).unwrap();
+        assert_eq!(board.turns(), &[0, 1, 0, 0]);
+        assert!(!board.is_solved());
+
+        let e = parse(text, GoalLayout::RowMajor, Variant::Slide).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 3));
+        let e = parse("2 2\n1 2:4\n3 0\n", GoalLayout::RowMajor, Variant::Rotate).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 5));
+        let e = parse("2 2\n1 2\n3 0:2\n", GoalLayout::RowMajor, Variant::Rotate).unwrap_err();
+        assert_eq!((e.line, e.column), (3, 3));
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let text = "# a comment\n\n3x3\n1 2 3\n\n4 5 6\n7 8 0\n";
+        let board = parse(text, GoalLayout::RowMajor, Variant::Slide).unwrap();
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn points_at_the_mistake() {
+        assert_eq!(error_at(""), (1, 1));
+        assert_eq!(error_at("3 3 3\n"), (1, 5));
+        assert_eq!(error_at("3 y\n"), (1, 3));
+        assert_eq!(error_at("3 3\n1 2 3\n4 5 6\n"), (4, 1));
+        assert_eq!(error_at("3 3\n1 2 3\n4 5\n7 8 0\n"), (3, 4));
+        assert_eq!(error_at("3 3\n1 2 3\n4 five 6\n7 8 0\n"), (3, 3));
+        assert_eq!(error_at("3 3\n1 2 3\n4 5 6\n7 9 0\n"), (4, 3));
+        assert_eq!(error_at("3 3\n1 2 3\n4 2 6\n7 8 0\n"), (3, 3));
+        assert_eq!(error_at("3 3\n1 2 3\n4 5 6\n7 8 0\n  1 2 3\n"), (5, 3));
+        assert_eq!(error_at("3 3\n1 2 3\n4 5 6\n8 7 0\n"), (1, 1));
+    }
+
+    #[test]
+    fn refuses_huge_boards() {
+        assert_eq!(error_at("100000 100000\n"), (1, 8));
+        assert_eq!(error_at("4294967295x4294967295\n"), (1, 12));
+        assert_eq!(error_at("101 100\n"), (1, 5));
+    }
+}
@@ -0,0 +1,179 @@
+extern crate dirs;
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use board::{GoalLayout, ShuffleMode, Variant};
+use grid::{FitMode, MoveMetric};
+
+
+//where everything the game keeps between runs goes
+pub fn data_path(file: &str) -> PathBuf {
+    let dir = match dirs::data_dir() {
+        Some(dir) => dir.join("slide-puzzle"),
+        None => PathBuf::from(".")
+    };
+    dir.join(file)
+}
+
+
+//a game in progress, written when the window closes mid game
+pub struct SavedGame {
+    pub width: u32,
+    pub height: u32,
+    //row major, None for the blank
+    pub cells: Vec<Option<u32>>,
+    //quarter turns of each cell, empty if nothing's turned
+    pub turns: Vec<u8>,
+    pub goal: GoalLayout,
+    pub variant: Variant,
+    pub move_counter: u32,
+    pub elapsed_ms: u64,
+    pub seed: u64,
+    pub shuffle: ShuffleMode,
+    pub metric: MoveMetric,
+    pub assisted: bool,
+    //the day it was the daily puzzle for
+    pub daily: Option<i64>,
+    pub move_budget: Option<u32>,
+    pub player_name: String,
+    pub img_path: String,
+    pub fit: FitMode
+}
+
+impl SavedGame {
+
+    fn path() -> PathBuf {
+        data_path("save.txt")
+    }
+
+    pub fn exists() -> bool {
+        SavedGame::path().is_file()
+    }
+
+    pub fn delete() {
+        if SavedGame::exists() {
+            if let Err(e) = fs::remove_file(SavedGame::path()) {
+                println!("Couldn't remove saved game: {}", e);
+            }
+        }
+    }
+
+    //one key=value per line, cells are written as tile numbers
+    //starting at 1 with 0 for the blank
+    pub fn save(&self) -> io::Result<()> {
+        let path = SavedGame::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let cells: Vec<String> = self.cells
+            .iter()
+            .map(|c| match c {
+                Some(tile) => (tile + 1).to_string(),
+                None => "0".to_string()
+            })
+            .collect();
+
+        let mut file = File::create(path)?;
+        writeln!(file, "width={}", self.width)?;
+        writeln!(file, "height={}", self.height)?;
+        writeln!(file, "cells={}", cells.join(" "))?;
+        if self.turns.iter().any(|&turn| turn != 0) {
+            let turns: Vec<String> = self.turns.iter().map(|turn| turn.to_string()).collect();
+            writeln!(file, "turns={}", turns.join(" "))?;
+        }
+        writeln!(file, "goal={}", self.goal.key())?;
+        writeln!(file, "variant={}", self.variant.key())?;
+        writeln!(file, "moves={}", self.move_counter)?;
+        writeln!(file, "elapsed_ms={}", self.elapsed_ms)?;
+        writeln!(file, "seed={}", self.seed)?;
+        writeln!(file, "shuffle={}", self.shuffle.key())?;
+        writeln!(file, "metric={}", self.metric.key())?;
+        writeln!(file, "assisted={}", self.assisted)?;
+        if let Some(day) = self.daily {
+            writeln!(file, "daily={}", day)?;
+        }
+        if let Some(budget) = self.move_budget {
+            writeln!(file, "move_budget={}", budget)?;
+        }
+        writeln!(file, "player={}", self.player_name)?;
+        writeln!(file, "image={}", self.img_path)?;
+        writeln!(file, "fit={}", self.fit.key())?;
+        Ok(())
+    }
+
+    pub fn load() -> io::Result<SavedGame> {
+        let bad_data = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let mut contents = String::new();
+        File::open(SavedGame::path())?.read_to_string(&mut contents)?;
+
+        let mut saved = SavedGame {
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+            turns: Vec::new(),
+            goal: GoalLayout::RowMajor,
+            variant: Variant::Slide,
+            move_counter: 0,
+            elapsed_ms: 0,
+            seed: 0,
+            shuffle: ShuffleMode::Uniform,
+            metric: MoveMetric::Mtm,
+            assisted: false,
+            daily: None,
+            move_budget: None,
+            player_name: String::new(),
+            img_path: String::new(),
+            fit: FitMode::Stretch
+        };
+
+        for line in contents.lines() {
+            let mut parts = line.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => continue
+            };
+
+            let bad_value = || bad_data(&format!("bad value for {}", key));
+            match key {
+                "width" => saved.width = value.parse().map_err(|_| bad_value())?,
+                "height" => saved.height = value.parse().map_err(|_| bad_value())?,
+                "cells" => {
+                    saved.cells = Vec::new();
+                    for tile in value.split_whitespace() {
+                        let tile: u32 = tile.parse().map_err(|_| bad_value())?;
+                        saved.cells.push(if tile == 0 { None } else { Some(tile - 1) });
+                    }
+                },
+                "turns" => {
+                    saved.turns = Vec::new();
+                    for turn in value.split_whitespace() {
+                        saved.turns.push(turn.parse().map_err(|_| bad_value())?);
+                    }
+                },
+                "goal" => saved.goal = GoalLayout::from_key(value).ok_or_else(bad_value)?,
+                "variant" => saved.variant = Variant::from_key(value).ok_or_else(bad_value)?,
+                "moves" => saved.move_counter = value.parse().map_err(|_| bad_value())?,
+                "elapsed_ms" => saved.elapsed_ms = value.parse().map_err(|_| bad_value())?,
+                "seed" => saved.seed = value.parse().map_err(|_| bad_value())?,
+                "shuffle" => saved.shuffle = ShuffleMode::from_key(value).ok_or_else(bad_value)?,
+                "metric" => saved.metric = MoveMetric::from_key(value).ok_or_else(bad_value)?,
+                "assisted" => saved.assisted = value == "true",
+                "daily" => saved.daily = Some(value.parse().map_err(|_| bad_value())?),
+                "move_budget" => saved.move_budget = Some(value.parse().map_err(|_| bad_value())?),
+                "player" => saved.player_name = value.to_string(),
+                "image" => saved.img_path = value.to_string(),
+                "fit" => saved.fit = FitMode::from_key(value).ok_or_else(bad_value)?,
+                _ => {}
+            }
+        }
+
+        if saved.width == 0 || saved.height == 0 || saved.img_path.is_empty() {
+            return Err(bad_data("saved game is missing its board or image"));
+        }
+
+        Ok(saved)
+    }
+}
@@ -0,0 +1,385 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use board::{GoalLayout, ShuffleMode, Variant};
+use grid::{GameSettings, MoveMetric};
+use date;
+use save;
+
+
+#[derive(Clone, Copy, Debug)]
+pub struct Score {
+    pub moves: u32,
+    pub time_ms: u64,
+    pub seed: u64
+}
+
+impl Score {
+    pub fn beats_moves(&self, other: &Option<Score>) -> bool {
+        match other {
+            Some(best) => self.moves < best.moves,
+            None => true
+        }
+    }
+
+    pub fn beats_time(&self, other: &Option<Score>) -> bool {
+        match other {
+            Some(best) => self.time_ms < best.time_ms,
+            None => true
+        }
+    }
+}
+
+
+//m:ss.mmm
+pub fn format_time(ms: u64) -> String {
+    format!("{}:{:02}.{:03}", ms / 60_000, (ms / 1000) % 60, ms % 1000)
+}
+
+
+//scores are only compared against games played with the same settings
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScoreKey {
+    pub width: u32,
+    pub height: u32,
+    pub blanks: u32,
+    pub goal: GoalLayout,
+    pub variant: Variant,
+    pub shuffle: ShuffleMode,
+    pub metric: MoveMetric,
+    pub image: String
+}
+
+impl ScoreKey {
+    pub fn new(settings: &GameSettings) -> ScoreKey {
+        //so ./res/sample.jpg and res/sample.jpg are the same image
+        let image = match fs::canonicalize(&settings.img_path) {
+            Ok(path) => path.to_string_lossy().into_owned(),
+            Err(_) => settings.img_path.clone()
+        };
+
+        ScoreKey {
+            width: settings.x_cells,
+            height: settings.y_cells,
+            blanks: settings.blanks,
+            goal: settings.goal,
+            variant: settings.variant,
+            shuffle: settings.shuffle,
+            metric: settings.metric,
+            image: image
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortBy {
+    Moves,
+    Time
+}
+
+impl SortBy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortBy::Moves => "Moves",
+            SortBy::Time => "Time"
+        }
+    }
+
+    pub fn next(&self) -> SortBy {
+        match self {
+            SortBy::Moves => SortBy::Time,
+            SortBy::Time => SortBy::Moves
+        }
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ScoreEntry {
+    pub key: ScoreKey,
+    pub score: Score,
+    //seconds since the unix epoch
+    pub date: u64,
+    pub name: String
+}
+
+
+//every finished game, saved in the user's data directory
+//one game per line as tab separated values
+pub struct ScoreBook {
+    path: PathBuf,
+    entries: Vec<ScoreEntry>
+}
+
+impl ScoreBook {
+
+    fn default_path() -> PathBuf {
+        save::data_path("scores.txt")
+    }
+
+    pub fn load() -> ScoreBook {
+        let path = ScoreBook::default_path();
+        let mut book = ScoreBook {
+            path: path,
+            entries: Vec::new()
+        };
+
+        let mut contents = String::new();
+        match File::open(&book.path) {
+            Ok(mut file) => {
+                if let Err(e) = file.read_to_string(&mut contents) {
+                    println!("Couldn't read scores: {}", e);
+                }
+            },
+            //nothing saved yet
+            Err(_) => {}
+        }
+
+        for (i, line) in contents.lines().enumerate() {
+            match ScoreBook::parse_line(line) {
+                Some(entry) => book.entries.push(entry),
+                None => println!("Skipping bad score on line {}", i + 1)
+            }
+        }
+
+        println!("Loaded {} scores from {:?}", book.entries.len(), book.path);
+        book
+    }
+
+    //width, height, shuffle, metric, moves, time, seed, date, name, blanks,
+    //goal, variant, image. the image is last since it's the only thing that
+    //might have a tab in it. older scores are missing the fields added after
+    //them: dates and names, then blanks, goals and variants
+    fn parse_line(line: &str) -> Option<ScoreEntry> {
+        let fields: Vec<&str> = line.splitn(13, '\t').collect();
+        let (date, name, blanks, goal, variant, image) = match fields.len() {
+            13 => (fields[7].parse().ok()?,
+                   fields[8],
+                   fields[9].parse().ok()?,
+                   GoalLayout::from_key(fields[10])?,
+                   Variant::from_key(fields[11])?,
+                   fields[12]),
+            12 => (fields[7].parse().ok()?,
+                   fields[8],
+                   fields[9].parse().ok()?,
+                   GoalLayout::from_key(fields[10])?,
+                   Variant::Slide,
+                   fields[11]),
+            11 => (fields[7].parse().ok()?, fields[8], fields[9].parse().ok()?,
+                   GoalLayout::RowMajor, Variant::Slide, fields[10]),
+            10 => (fields[7].parse().ok()?, fields[8], 1, GoalLayout::RowMajor,
+                   Variant::Slide, fields[9]),
+            8 => (0, "", 1, GoalLayout::RowMajor, Variant::Slide, fields[7]),
+            _ => return None
+        };
+
+        Some(ScoreEntry {
+            key: ScoreKey {
+                width: fields[0].parse().ok()?,
+                height: fields[1].parse().ok()?,
+                blanks: blanks,
+                goal: goal,
+                variant: variant,
+                shuffle: ShuffleMode::from_key(fields[2])?,
+                metric: MoveMetric::from_key(fields[3])?,
+                image: image.to_string()
+            },
+            score: Score {
+                moves: fields[4].parse().ok()?,
+                time_ms: fields[5].parse().ok()?,
+                seed: fields[6].parse().ok()?
+            },
+            date: date,
+            name: name.to_string()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = File::create(&self.path)?;
+        for entry in &self.entries {
+            writeln!(file, "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                     entry.key.width,
+                     entry.key.height,
+                     entry.key.shuffle.key(),
+                     entry.key.metric.key(),
+                     entry.score.moves,
+                     entry.score.time_ms,
+                     entry.score.seed,
+                     entry.date,
+                     entry.name,
+                     entry.key.blanks,
+                     entry.key.goal.key(),
+                     entry.key.variant.key(),
+                     entry.key.image)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn add(&mut self, key: ScoreKey, score: Score, name: &str) {
+        //tabs and newlines would break the file
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+
+        self.entries.push(ScoreEntry {
+            key: key,
+            score: score,
+            date: date::now_secs(),
+            name: name
+        });
+        if let Err(e) = self.save() {
+            println!("Couldn't save scores: {}", e);
+        }
+    }
+
+    fn matching<'a>(&'a self, key: &'a ScoreKey) -> impl Iterator<Item = &'a Score> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| &entry.key == key)
+            .map(|entry| &entry.score)
+    }
+
+    pub fn best_moves(&self, key: &ScoreKey) -> Option<Score> {
+        self.matching(key)
+            .min_by_key(|score| (score.moves, score.time_ms))
+            .cloned()
+    }
+
+    pub fn best_time(&self, key: &ScoreKey) -> Option<Score> {
+        self.matching(key)
+            .min_by_key(|score| (score.time_ms, score.moves))
+            .cloned()
+    }
+
+    //name on the most recent game, to default to next time
+    pub fn last_name(&self) -> Option<String> {
+        self.entries
+            .iter()
+            .rev()
+            .map(|entry| entry.name.clone())
+            .find(|name| !name.is_empty())
+    }
+
+    //every set of settings that has a score, smallest boards first
+    pub fn keys(&self) -> Vec<ScoreKey> {
+        let mut keys: Vec<ScoreKey> = Vec::new();
+        for entry in &self.entries {
+            if !keys.contains(&entry.key) {
+                keys.push(entry.key.clone());
+            }
+        }
+        keys.sort_by_key(|key| (key.width * key.height, key.width, key.height));
+        keys
+    }
+
+    //only games played with the same settings are ranked together
+    pub fn top(&self, key: &ScoreKey, sort: SortBy, count: usize) -> Vec<ScoreEntry> {
+        let mut entries: Vec<ScoreEntry> = self.entries
+            .iter()
+            .filter(|entry| &entry.key == key)
+            .cloned()
+            .collect();
+
+        match sort {
+            SortBy::Moves => entries.sort_by_key(|e| (e.score.moves, e.score.time_ms)),
+            SortBy::Time => entries.sort_by_key(|e| (e.score.time_ms, e.score.moves))
+        }
+        entries.truncate(count);
+        entries
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_line_format() {
+        //oldest first, each adding fields before the image
+        let lines = [
+            "4\t4\tuniform\tmtm\t80\t61000\t9\tres/a.jpg",
+            "4\t4\tuniform\tmtm\t80\t61000\t9\t1600000000\tsam\tres/a.jpg",
+            "4\t4\tuniform\tmtm\t80\t61000\t9\t1600000000\tsam\t2\tres/a.jpg",
+            "4\t4\tuniform\tmtm\t80\t61000\t9\t1600000000\tsam\t2\tspiral\tres/a.jpg",
+            "4\t4\tuniform\tmtm\t80\t61000\t9\t1600000000\tsam\t2\tspiral\twrap\tres/a.jpg"
+        ];
+        let expected = [
+            (0, "", 1, GoalLayout::RowMajor, Variant::Slide),
+            (1_600_000_000, "sam", 1, GoalLayout::RowMajor, Variant::Slide),
+            (1_600_000_000, "sam", 2, GoalLayout::RowMajor, Variant::Slide),
+            (1_600_000_000, "sam", 2, GoalLayout::Spiral, Variant::Slide),
+            (1_600_000_000, "sam", 2, GoalLayout::Spiral, Variant::Wrap)
+        ];
+
+        for (line, &(date, name, blanks, goal, variant)) in lines.iter().zip(expected.iter()) {
+            let entry = ScoreBook::parse_line(line).expect(line);
+            assert_eq!(entry.key.width, 4);
+            assert_eq!(entry.key.height, 4);
+            assert_eq!(entry.key.shuffle, ShuffleMode::Uniform);
+            assert_eq!(entry.key.metric, MoveMetric::Mtm);
+            assert_eq!(entry.key.blanks, blanks);
+            assert_eq!(entry.key.goal, goal);
+            assert_eq!(entry.key.variant, variant);
+            assert_eq!(entry.key.image, "res/a.jpg");
+            assert_eq!((entry.score.moves, entry.score.time_ms, entry.score.seed), (80, 61000, 9));
+            assert_eq!(entry.date, date);
+            assert_eq!(entry.name, name);
+        }
+    }
+
+    #[test]
+    fn image_can_have_tabs() {
+        let line = "3\t3\tscramble\tstm\t20\t5000\t1\t0\t\t1\trows\tslide\tmy\tpicture.png";
+        let entry = ScoreBook::parse_line(line).unwrap();
+        assert_eq!(entry.key.image, "my\tpicture.png");
+        assert_eq!(entry.key.shuffle, ShuffleMode::Scramble);
+        assert_eq!(entry.key.metric, MoveMetric::Stm);
+    }
+
+    #[test]
+    fn skips_bad_lines() {
+        assert!(ScoreBook::parse_line("").is_none());
+        assert!(ScoreBook::parse_line("4\t4\tuniform").is_none());
+        assert!(ScoreBook::parse_line("4\t4\tsideways\tmtm\t80\t61000\t9\tres/a.jpg").is_none());
+        assert!(ScoreBook::parse_line("4\tx\tuniform\tmtm\t80\t61000\t9\tres/a.jpg").is_none());
+    }
+
+    #[test]
+    fn ranks_only_matching_settings() {
+        let lines = [
+            "4\t4\tuniform\tmtm\t80\t61000\t1\tres/a.jpg",
+            "4\t4\tscramble\tmtm\t20\t9000\t2\tres/a.jpg",
+            "4\t4\tuniform\tstm\t30\t9000\t3\tres/a.jpg",
+            "4\t4\tuniform\tmtm\t60\t70000\t4\tres/a.jpg",
+            "4\t4\tuniform\tmtm\t10\t5000\t5\tres/b.jpg",
+            "3\t3\tuniform\tmtm\t15\t4000\t6\tres/a.jpg"
+        ];
+        let book = ScoreBook {
+            path: PathBuf::new(),
+            entries: lines.iter().map(|line| ScoreBook::parse_line(line).unwrap()).collect()
+        };
+
+        let keys = book.keys();
+        assert_eq!(keys.len(), 5);
+        assert_eq!((keys[0].width, keys[0].height), (3, 3));
+
+        let key = book.entries[0].key.clone();
+        let seeds: Vec<u64> = book.top(&key, SortBy::Moves, 8).iter().map(|entry| entry.score.seed).collect();
+        assert_eq!(seeds, vec![4, 1]);
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_time(0), "0:00.000");
+        assert_eq!(format_time(61_005), "1:01.005");
+        assert_eq!(format_time(3_600_000), "60:00.000");
+    }
+}
@@ -0,0 +1,458 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use board::{Board, GoalLayout};
+use pattern_db::PatternDb;
+
+
+//boards up to this many cells solve quickly on the plain heuristic,
+//anything bigger needs a pattern database to finish in reasonable time
+pub const MAX_CELLS_WITHOUT_DB: u32 = 12;
+
+
+//optimal solver for a board using IDA*,
+//with manhattan distance plus linear conflict as the heuristic.
+//on a wrapping board distances are taken the short way round and
+//linear conflict is left out, since tiles can get past each other
+//moves are returned as the cell to click (the tile that slides
+//into the blank), so they can be fed straight into a click.
+//a pattern database for this board size is used when one is given,
+//taking whichever estimate is higher. setting cancel from another
+//thread stops the search, which then gives None
+pub fn solve_with(board: &Board,
+                  db: Option<&PatternDb>,
+                  cancel: &AtomicBool) -> Option<Vec<(u32, u32)>> {
+    //the search would never finish otherwise
+    if !board.is_solvable() {
+        return None;
+    }
+    //the search only knows how to move a single blank, not turn tiles
+    if board.blank_count() != 1 || board.rotates() {
+        return None;
+    }
+
+    let mut search = Search::new(board, usable_db(board, db), cancel);
+    search.run()
+}
+
+//without a database and with no way to stop it, for the tests
+#[cfg(test)]
+pub fn solve(board: &Board) -> Option<Vec<(u32, u32)>> {
+    solve_with(board, None, &AtomicBool::new(false))
+}
+
+//the databases are built for the usual goal without wrapping
+fn usable_db<'a>(board: &Board, db: Option<&'a PatternDb>) -> Option<&'a PatternDb> {
+    db.filter(|db| db.width == board.width && db.height == board.height &&
+                   board.goal() == GoalLayout::RowMajor && !board.wraps())
+}
+
+//whether solve_with can be expected to finish: small boards always
+//do, bigger ones only with a pattern database that fits them
+pub fn is_practical(board: &Board, db: Option<&PatternDb>) -> bool {
+    board.width * board.height <= MAX_CELLS_WITHOUT_DB || usable_db(board, db).is_some()
+}
+
+
+enum Step {
+    Found,
+    Next(u32),
+    Cancelled
+}
+
+
+struct Search<'a> {
+    width: usize,
+    height: usize,
+    wrap: bool,
+    //goal index of the tile at every position, so the search works
+    //the same whatever the layout of the goal is
+    tiles: Vec<usize>,
+    blank: usize,
+    //goal index of the blank
+    blank_goal: usize,
+    manhattan: u32,
+    row_conflicts: Vec<u32>,
+    col_conflicts: Vec<u32>,
+    conflicts: u32,
+    db: Option<&'a PatternDb>,
+    //position of every tile, indexed by tile
+    positions: Vec<usize>,
+    group_costs: Vec<u32>,
+    pattern_cost: u32,
+    path: Vec<usize>,
+    cancel: &'a AtomicBool
+}
+
+impl<'a> Search<'a> {
+
+    fn new(board: &Board, db: Option<&'a PatternDb>, cancel: &'a AtomicBool) -> Search<'a> {
+        let (width, height) = (board.width as usize, board.height as usize);
+        let goal = board.goal_cells();
+        let blank_goal = goal.iter().position(|c| c.is_none()).unwrap();
+        let mut goal_index = vec![0; width * height];
+        for (i, content) in goal.iter().enumerate() {
+            if let Some(tile) = content {
+                goal_index[*tile as usize] = i;
+            }
+        }
+
+        let tiles: Vec<usize> = board.cells()
+            .iter()
+            .map(|content| match content {
+                Some(i) => goal_index[*i as usize],
+                None => blank_goal
+            })
+            .collect();
+
+        let (ex, ey) = board.empty();
+
+        let mut search = Search {
+            width: width,
+            height: height,
+            wrap: board.wraps(),
+            tiles: tiles,
+            blank: ex as usize + ey as usize * width,
+            blank_goal: blank_goal,
+            manhattan: 0,
+            row_conflicts: vec![0; height],
+            col_conflicts: vec![0; width],
+            conflicts: 0,
+            db: db,
+            positions: vec![0; width * height],
+            group_costs: Vec::new(),
+            pattern_cost: 0,
+            path: Vec::new(),
+            cancel: cancel
+        };
+
+        for pos in 0..search.tiles.len() {
+            search.positions[search.tiles[pos]] = pos;
+        }
+        if let Some(db) = db {
+            search.group_costs = (0..db.group_count())
+                .map(|group| db.lookup(group, &search.positions))
+                .collect();
+            search.pattern_cost = search.group_costs.iter().sum();
+        }
+
+        for pos in 0..search.tiles.len() {
+            if pos != search.blank {
+                search.manhattan += search.distance(search.tiles[pos], pos);
+            }
+        }
+        if !search.wrap {
+            for row in 0..height {
+                search.row_conflicts[row] = search.row_conflict(row);
+            }
+            for col in 0..width {
+                search.col_conflicts[col] = search.col_conflict(col);
+            }
+            search.conflicts = search.row_conflicts.iter().sum::<u32>() +
+                               search.col_conflicts.iter().sum::<u32>();
+        }
+
+        search
+    }
+
+
+    fn heuristic(&self) -> u32 {
+        let estimate = self.manhattan + 2 * self.conflicts;
+        if self.pattern_cost > estimate {
+            self.pattern_cost
+        } else {
+            estimate
+        }
+    }
+
+
+    fn distance(&self, tile: usize, pos: usize) -> u32 {
+        let (gx, gy) = (tile % self.width, tile / self.width);
+        let (px, py) = (pos % self.width, pos / self.width);
+        let dx = (gx as i32 - px as i32).abs() as u32;
+        let dy = (gy as i32 - py as i32).abs() as u32;
+
+        if self.wrap {
+            let (w, h) = (self.width as u32, self.height as u32);
+            dx.min(w - dx) + dy.min(h - dy)
+        } else {
+            dx + dy
+        }
+    }
+
+
+    //tiles that have to leave a line so the rest of the tiles
+    //whose goal is in that line are in order, ie length - LIS
+    fn line_conflict(goals: &[usize]) -> u32 {
+        let mut longest: Vec<u32> = Vec::with_capacity(goals.len());
+        let mut best = 0;
+
+        for i in 0..goals.len() {
+            let mut len = 1;
+            for j in 0..i {
+                if goals[j] < goals[i] && longest[j] + 1 > len {
+                    len = longest[j] + 1;
+                }
+            }
+            longest.push(len);
+            if len > best {
+                best = len;
+            }
+        }
+
+        goals.len() as u32 - best
+    }
+
+    fn row_conflict(&self, row: usize) -> u32 {
+        let goals: Vec<usize> = (0..self.width)
+            .map(|x| self.tiles[x + row * self.width])
+            .filter(|&t| t != self.blank_goal && t / self.width == row)
+            .map(|t| t % self.width)
+            .collect();
+
+        Search::line_conflict(&goals)
+    }
+
+    fn col_conflict(&self, col: usize) -> u32 {
+        let goals: Vec<usize> = (0..self.height)
+            .map(|y| self.tiles[col + y * self.width])
+            .filter(|&t| t != self.blank_goal && t % self.width == col)
+            .map(|t| t / self.width)
+            .collect();
+
+        Search::line_conflict(&goals)
+    }
+
+
+    fn neighbours(&self) -> Vec<usize> {
+        let (bx, by) = (self.blank % self.width, self.blank / self.width);
+        let mut moves = Vec::with_capacity(4);
+
+        if by > 0 {
+            moves.push(self.blank - self.width);
+        }
+        if by < self.height - 1 {
+            moves.push(self.blank + self.width);
+        }
+        if bx > 0 {
+            moves.push(self.blank - 1);
+        }
+        if bx < self.width - 1 {
+            moves.push(self.blank + 1);
+        }
+
+        if self.wrap {
+            if by == 0 {
+                moves.push(self.blank + (self.height - 1) * self.width);
+            }
+            if by == self.height - 1 {
+                moves.push(self.blank - (self.height - 1) * self.width);
+            }
+            if bx == 0 {
+                moves.push(self.blank + self.width - 1);
+            }
+            if bx == self.width - 1 {
+                moves.push(self.blank + 1 - self.width);
+            }
+            //on a side of two the way round is the same cell
+            moves.retain(|&pos| pos != self.blank);
+            moves.sort();
+            moves.dedup();
+        }
+
+        moves
+    }
+
+
+    //slide the tile at pos into the blank,
+    //keeping the heuristic up to date
+    fn slide(&mut self, pos: usize) {
+        let tile = self.tiles[pos];
+        let old_blank = self.blank;
+
+        self.manhattan = self.manhattan + self.distance(tile, old_blank) -
+                         self.distance(tile, pos);
+
+        self.tiles[old_blank] = tile;
+        self.tiles[pos] = self.blank_goal;
+        self.blank = pos;
+        self.positions[tile] = old_blank;
+
+        if let Some(db) = self.db {
+            if let Some(group) = db.group_of(tile as u32) {
+                let cost = db.lookup(group, &self.positions);
+                self.pattern_cost = self.pattern_cost + cost - self.group_costs[group];
+                self.group_costs[group] = cost;
+            }
+        }
+
+        //a horizontal slide keeps the order of its row,
+        //so only the two columns can change and vice versa
+        if self.wrap {
+            return;
+        }
+        if pos / self.width == old_blank / self.width {
+            for &col in &[pos % self.width, old_blank % self.width] {
+                let conflict = self.col_conflict(col);
+                self.conflicts = self.conflicts + conflict - self.col_conflicts[col];
+                self.col_conflicts[col] = conflict;
+            }
+        } else {
+            for &row in &[pos / self.width, old_blank / self.width] {
+                let conflict = self.row_conflict(row);
+                self.conflicts = self.conflicts + conflict - self.row_conflicts[row];
+                self.row_conflicts[row] = conflict;
+            }
+        }
+    }
+
+
+    fn dfs(&mut self, cost: u32, bound: u32, prev_blank: Option<usize>) -> Step {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Step::Cancelled;
+        }
+
+        let estimate = cost + self.heuristic();
+        if estimate > bound {
+            return Step::Next(estimate);
+        }
+        if self.heuristic() == 0 {
+            return Step::Found;
+        }
+
+        let mut next_bound = u32::MAX;
+        for pos in self.neighbours() {
+            //never slide straight back
+            if Some(pos) == prev_blank {
+                continue;
+            }
+
+            let blank = self.blank;
+            self.slide(pos);
+            self.path.push(pos);
+
+            match self.dfs(cost + 1, bound, Some(blank)) {
+                Step::Found => return Step::Found,
+                Step::Cancelled => return Step::Cancelled,
+                Step::Next(b) => {
+                    if b < next_bound {
+                        next_bound = b;
+                    }
+                }
+            }
+
+            self.path.pop();
+            self.slide(blank);
+        }
+
+        Step::Next(next_bound)
+    }
+
+
+    fn run(&mut self) -> Option<Vec<(u32, u32)>> {
+        let mut bound = self.heuristic();
+
+        loop {
+            match self.dfs(0, bound, None) {
+                Step::Found => {
+                    let width = self.width;
+                    return Some(self.path
+                                .iter()
+                                .map(|&pos| ((pos % width) as u32,
+                                             (pos / width) as u32))
+                                .collect());
+                },
+                Step::Next(b) => {
+                    if b == u32::MAX {
+                        return None;
+                    }
+                    bound = b;
+                },
+                Step::Cancelled => return None
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::collections::{HashMap, VecDeque};
+    use board::{self, GoalLayout, ShuffleMode, Variant};
+
+    //the fewest moves back to the goal from every position that can reach it
+    pub fn distances(goal: &Board) -> HashMap<Board, u32> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(goal.clone(), 0);
+        queue.push_back(goal.clone());
+
+        while let Some(board) = queue.pop_front() {
+            let distance = distances[&board];
+            for (x, y) in board.legal_moves() {
+                let mut next = board.clone();
+                next.apply_move(x, y);
+                if !distances.contains_key(&next) {
+                    distances.insert(next.clone(), distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        distances
+    }
+
+    //the moves have to solve the board, and there can't be fewer of them
+    fn check_optimal(board: &Board, distance: u32) {
+        let moves = solve(board).expect("no solution found");
+        assert_eq!(moves.len() as u32, distance, "not optimal for {:?}", board);
+
+        let mut board = board.clone();
+        for (x, y) in moves {
+            assert!(board.apply_move(x, y));
+        }
+        assert!(board.is_solved());
+    }
+
+    #[test]
+    fn solves_every_small_board_optimally() {
+        for &(w, h) in &[(3, 2), (2, 3)] {
+            for (board, &distance) in &distances(&Board::new(w, h)) {
+                check_optimal(board, distance);
+            }
+        }
+    }
+
+    #[test]
+    fn solves_every_small_wrap_board_optimally() {
+        let goal = Board::with_goal(3, 2, 1, GoalLayout::RowMajor, Variant::Wrap);
+        for (board, &distance) in &distances(&goal) {
+            check_optimal(board, distance);
+        }
+    }
+
+    #[test]
+    fn solves_shuffled_3x3_boards_optimally() {
+        let goal = Board::new(3, 3);
+        let distances = distances(&goal);
+        let mut rng = board::seeded_rng(2);
+        for _ in 0..20 {
+            let mut board = goal.clone();
+            board.shuffle_with(ShuffleMode::Uniform, &mut rng);
+            check_optimal(&board, distances[&board]);
+        }
+    }
+
+    #[test]
+    fn cancelled_search_gives_up() {
+        let mut board = Board::new(4, 4);
+        board.shuffle_with(ShuffleMode::Uniform, &mut board::seeded_rng(1));
+        assert_eq!(solve_with(&board, None, &AtomicBool::new(true)), None);
+    }
+
+    #[test]
+    fn big_boards_need_a_database() {
+        assert!(is_practical(&Board::new(4, 3), None));
+        assert!(!is_practical(&Board::new(4, 4), None));
+        assert!(!is_practical(&Board::new(6, 6), None));
+    }
+}
@@ -6,6 +6,65 @@ use opengl_graphics::{GlGraphics, GlyphCache};
 use graphics::*;
 use graphics::character::CharacterCache;
 use tinyfiledialogs;
+use std::env;
+use board::{GoalLayout, ShuffleMode, Variant};
+use score::{self, Score, ScoreBook, ScoreKey};
+use grid::{FitMode, GameSettings, KeyMode, MoveMetric, SlideSpeed};
+use save::SavedGame;
+use daily::{self, DailyBook, DailyEntry};
+
+
+//the title and leaderboard are laid out for a window this size,
+//then scaled to fit the real one and centered in it
+pub const LAYOUT_WIDTH: u32 = 400;
+pub const LAYOUT_HEIGHT: u32 = 500;
+
+pub struct Layout {
+    pub scale: f64,
+    pub x: f64,
+    pub y: f64
+}
+
+impl Layout {
+    pub fn fit(w: u32, h: u32) -> Layout {
+        let scale_x = w as f64 / LAYOUT_WIDTH as f64;
+        let scale_y = h as f64 / LAYOUT_HEIGHT as f64;
+        let scale = if scale_x < scale_y { scale_x } else { scale_y };
+
+        Layout {
+            scale: scale,
+            x: (w as f64 - LAYOUT_WIDTH as f64 * scale) / 2.0,
+            y: (h as f64 - LAYOUT_HEIGHT as f64 * scale) / 2.0
+        }
+    }
+
+    pub fn transform(&self, t: &math::Matrix2d) -> math::Matrix2d {
+        t.trans(self.x, self.y).scale(self.scale, self.scale)
+    }
+
+    //render args for drawing in layout coordinates
+    pub fn args(&self, args: &RenderArgs) -> RenderArgs {
+        RenderArgs {
+            width: LAYOUT_WIDTH,
+            height: LAYOUT_HEIGHT,
+            ..*args
+        }
+    }
+
+    //a point in the window in layout coordinates. points
+    //in the margins end up where there's nothing to hit
+    pub fn to_layout(&self, x: f64, y: f64) -> (u32, u32) {
+        let to_layout = |pos: f64, offset: f64, size: u32| {
+            let pos = (pos - offset) / self.scale;
+            if pos < 0.0 || pos > size as f64 || !pos.is_finite() {
+                u32::MAX
+            } else {
+                pos as u32
+            }
+        };
+        (to_layout(x, self.x, LAYOUT_WIDTH), to_layout(y, self.y, LAYOUT_HEIGHT))
+    }
+}
 
 
 pub enum ButtonPos {
@@ -121,6 +180,11 @@ impl Button {
 
 pub enum TitleEvent {
     PlayClick,
+    DailyClick,
+    ContinueClick,
+    //a board picked from a text file
+    LoadClick(String),
+    LeaderboardClick,
     NoEvent
 }
 
@@ -128,13 +192,48 @@ pub enum TitleEvent {
 pub struct Title {
     pub grid_w: u32,
     pub grid_h: u32,
+    pub blanks: u32,
+    pub goal: GoalLayout,
+    pub variant: Variant,
     pub grid_img_path: String,
-    pub high_score: u32,
+    pub fit: FitMode,
+    pub shuffle: ShuffleMode,
+    pub metric: MoveMetric,
+    pub key_mode: KeyMode,
+    pub speed: SlideSpeed,
+    //None picks a new random seed for every game
+    pub seed: Option<u64>,
+    pub player_name: String,
+    //moves allowed over an optimal solution, None for no limit
+    pub move_limit: Option<u32>,
+    //best results for the settings currently picked
+    pub high_score: Option<Score>,
+    pub best_time: Option<Score>,
+    //today's daily result for this player and their streak
+    pub daily_result: Option<DailyEntry>,
+    pub daily_streak: u32,
+    //there's a game left over from last time
+    can_continue: bool,
     logo: opengl_graphics::Texture,
     play_btn: Button,
+    daily_btn: Button,
+    continue_btn: Button,
     file_choose_btn: Button,
+    load_btn: Button,
+    leaderboard_btn: Button,
     width_btn: Button,
-    height_btn: Button
+    height_btn: Button,
+    blanks_btn: Button,
+    goal_btn: Button,
+    variant_btn: Button,
+    shuffle_btn: Button,
+    seed_btn: Button,
+    metric_btn: Button,
+    key_mode_btn: Button,
+    name_btn: Button,
+    limit_btn: Button,
+    fit_btn: Button,
+    speed_btn: Button
 }
 
 impl Title {
@@ -162,9 +261,26 @@ impl Title {
         clear(bg_col, gl);
 
         self.play_btn.render(gl,t,glyph,args);
+        self.daily_btn.render(gl,t,glyph,args);
+        if self.can_continue {
+            self.continue_btn.render(gl,t,glyph,args);
+        }
         self.file_choose_btn.render(gl, t, glyph, args);
+        self.load_btn.render(gl, t, glyph, args);
+        self.leaderboard_btn.render(gl, t, glyph, args);
         self.width_btn.render(gl,t,glyph,args);
         self.height_btn.render(gl,t,glyph,args);
+        self.blanks_btn.render(gl,t,glyph,args);
+        self.goal_btn.render(gl,t,glyph,args);
+        self.variant_btn.render(gl,t,glyph,args);
+        self.shuffle_btn.render(gl,t,glyph,args);
+        self.seed_btn.render(gl,t,glyph,args);
+        self.metric_btn.render(gl,t,glyph,args);
+        self.key_mode_btn.render(gl,t,glyph,args);
+        self.name_btn.render(gl,t,glyph,args);
+        self.limit_btn.render(gl,t,glyph,args);
+        self.fit_btn.render(gl,t,glyph,args);
+        self.speed_btn.render(gl,t,glyph,args);
 
         let (screen_width, screen_height) = (args.width as f64,
                                              args.height as f64);
@@ -178,7 +294,7 @@ impl Title {
               gl); 
 
 
-        let text_content = &format!("Path:{}", &self.grid_img_path);
+        let text_content = &format!("Path:{} ({})", &self.grid_img_path, self.fit.name());
 
         Title::render_text(text_content,
                            glyph,
@@ -186,19 +302,91 @@ impl Title {
                            24,
                            gl);
 
-        let text_content = &format!("W:{}, H:{}", self.grid_w, self.grid_h);
+        let mut text_content = format!("W:{}, H:{}, {}, {}",
+                                       self.grid_w,
+                                       self.grid_h,
+                                       self.shuffle.name(),
+                                       self.metric.name());
+        //the less usual settings only show up when they're picked
+        if self.blanks > 1 || self.goal != GoalLayout::RowMajor || self.variant != Variant::Slide {
+            let mut parts = vec![format!("{}x{}", self.grid_w, self.grid_h)];
+            if self.variant != Variant::Slide {
+                parts.push(self.variant.name().to_string());
+            }
+            if self.blank_cells() > 1 {
+                parts.push(format!("{} Blanks", self.blanks));
+            }
+            if self.goal != GoalLayout::RowMajor {
+                parts.push(self.goal.name().to_string());
+            }
+            parts.push(self.shuffle.name().to_string());
+            parts.push(self.metric.name().to_string());
+            text_content = parts.join(", ");
+        }
+        let text_content = &text_content;
         Title::render_text(text_content,
                            glyph,
                            t.trans(5.0, screen_height - 50.0),
                            24,
                            gl);
 
-        let text_content = &format!("High Score: {}", self.high_score);
+        let text_content = &match self.high_score {
+            Some(score) => format!("High Score: {} (Seed {})", score.moves, score.seed),
+            None => "High Score: 0".to_string()
+        };
         Title::render_text(text_content,
                            glyph,
                            t.trans(5.0, screen_height - 75.0),
                            24,
                            gl);
+
+        let text_content = &match self.best_time {
+            Some(score) => format!("Best Time: {} (Seed {})",
+                                   score::format_time(score.time_ms),
+                                   score.seed),
+            None => "Best Time: -".to_string()
+        };
+        Title::render_text(text_content,
+                           glyph,
+                           t.trans(5.0, screen_height - 100.0),
+                           24,
+                           gl);
+
+        let text_content = &match self.seed {
+            Some(seed) => format!("Seed: {}, {}", seed, self.key_mode.name()),
+            None => format!("Seed: Random, {}", self.key_mode.name())
+        };
+        Title::render_text(text_content,
+                           glyph,
+                           t.trans(5.0, screen_height - 125.0),
+                           24,
+                           gl);
+
+        let text_content = &match self.move_limit {
+            Some(slack) => format!("Player: {}, {} Slides, Limit: +{}",
+                                   self.player_name,
+                                   self.speed.name(),
+                                   slack),
+            None => format!("Player: {}, {} Slides", self.player_name, self.speed.name())
+        };
+        Title::render_text(text_content,
+                           glyph,
+                           t.trans(5.0, screen_height - 150.0),
+                           24,
+                           gl);
+
+        let text_content = &match &self.daily_result {
+            Some(entry) => format!("Daily: {} in {}, streak {}",
+                                   entry.score.moves,
+                                   score::format_time(entry.score.time_ms),
+                                   self.daily_streak),
+            None => format!("Daily: not played, streak {}", self.daily_streak)
+        };
+        Title::render_text(text_content,
+                           glyph,
+                           t.trans(5.0, screen_height - 175.0),
+                           24,
+                           gl);
     }
 
     pub fn choose_file(path: Option<String>) -> Option<String> {
@@ -220,6 +408,53 @@ impl Title {
         result
     }
 
+    //loopover boards don't have a blank whatever B is set to
+    pub fn blank_cells(&self) -> u32 {
+        if self.variant == Variant::Loopover { 0 } else { self.blanks }
+    }
+
+    pub fn settings(&self) -> GameSettings {
+        GameSettings {
+            x_cells: self.grid_w,
+            y_cells: self.grid_h,
+            blanks: self.blank_cells(),
+            goal: self.goal,
+            variant: self.variant,
+            shuffle: self.shuffle,
+            metric: self.metric,
+            img_path: self.grid_img_path.clone(),
+            fit: self.fit
+        }
+    }
+
+    pub fn score_key(&self) -> ScoreKey {
+        ScoreKey::new(&self.settings())
+    }
+
+    pub fn refresh_scores(&mut self, scores: &ScoreBook) {
+        let key = self.score_key();
+        self.high_score = scores.best_moves(&key);
+        self.best_time = scores.best_time(&key);
+    }
+
+    pub fn refresh_daily(&mut self, book: &DailyBook) {
+        let today = daily::today();
+        self.daily_result = book.result(today, &self.player_name, self.metric).cloned();
+        self.daily_streak = book.streak(today, &self.player_name);
+    }
+
+    //check for a saved game, the other buttons move over to make room for continue
+    pub fn refresh_continue(&mut self) {
+        self.can_continue = SavedGame::exists();
+        if self.can_continue {
+            self.play_btn.pos = ButtonPos::CenteredOffset((-130, 145));
+            self.daily_btn.pos = ButtonPos::CenteredOffset((0, 145));
+        } else {
+            self.play_btn.pos = ButtonPos::CenteredOffset((-65, 145));
+            self.daily_btn.pos = ButtonPos::CenteredOffset((65, 145));
+        }
+    }
+
     pub fn update(&mut self) {
 
     }
@@ -229,6 +464,25 @@ impl Title {
             return TitleEvent::PlayClick;
         }
 
+        if self.daily_btn.in_bound(raw_x, raw_y, w, h) {
+            return TitleEvent::DailyClick;
+        }
+
+        if self.can_continue && self.continue_btn.in_bound(raw_x, raw_y, w, h) {
+            return TitleEvent::ContinueClick;
+        }
+
+        if self.leaderboard_btn.in_bound(raw_x, raw_y, w, h) {
+            return TitleEvent::LeaderboardClick;
+        }
+
+        if self.load_btn.in_bound(raw_x, raw_y, w, h) {
+            let fl = tinyfiledialogs::open_file_dialog("Load Board", "./", None);
+            if let Some(pth) = fl {
+                return TitleEvent::LoadClick(pth);
+            }
+        }
+
         if self.file_choose_btn.in_bound(raw_x, raw_y, w, h) {
             let fl = Self::choose_file(Some("./res/sample.jpg".to_string()));
             println!("{:?}", fl);
@@ -261,54 +515,285 @@ impl Title {
             }
         }
 
+        if self.blanks_btn.in_bound(raw_x, raw_y, w, h) {
+            let new_blanks = Self::input_dialog("Enter Number of Blanks",
+                                                Some(self.blanks.to_string()));
+            if let Some(mut new_blanks) = new_blanks {
+                new_blanks.retain(|c| c.is_numeric());
+                match new_blanks.parse::<u32>() {
+                    Ok(b) if b > 0 => {self.blanks = b;},
+                    Ok(_) => {},
+                    Err(e) => {println!("{:?}", e);}
+                }
+            }
+        }
+
+        if self.goal_btn.in_bound(raw_x, raw_y, w, h) {
+            self.goal = self.goal.next();
+        }
+
+        if self.variant_btn.in_bound(raw_x, raw_y, w, h) {
+            self.variant = self.variant.next();
+        }
+
+        if self.shuffle_btn.in_bound(raw_x, raw_y, w, h) {
+            self.shuffle = self.shuffle.next();
+        }
+
+        if self.metric_btn.in_bound(raw_x, raw_y, w, h) {
+            self.metric = self.metric.next();
+        }
+
+        if self.fit_btn.in_bound(raw_x, raw_y, w, h) {
+            self.fit = self.fit.next();
+        }
+
+        if self.key_mode_btn.in_bound(raw_x, raw_y, w, h) {
+            self.key_mode = self.key_mode.next();
+        }
+
+        if self.speed_btn.in_bound(raw_x, raw_y, w, h) {
+            self.speed = self.speed.next();
+        }
+
+        if self.name_btn.in_bound(raw_x, raw_y, w, h) {
+            let new_name = Self::input_dialog("Enter Player Name",
+                                              Some(self.player_name.clone()));
+            if let Some(new_name) = new_name {
+                let new_name = new_name.trim();
+                if !new_name.is_empty() {
+                    self.player_name = new_name.to_string();
+                }
+            }
+        }
+
+        if self.limit_btn.in_bound(raw_x, raw_y, w, h) {
+            let default = self.move_limit.map(|slack| slack.to_string());
+            let new_limit = Self::input_dialog("Moves allowed over the best solution (blank for no limit)",
+                                               default);
+            if let Some(mut new_limit) = new_limit {
+                new_limit.retain(|c| c.is_numeric());
+                if new_limit.is_empty() {
+                    self.move_limit = None;
+                } else {
+                    match new_limit.parse::<u32>() {
+                        Ok(slack) => {self.move_limit = Some(slack);},
+                        Err(e) => {println!("{:?}", e);}
+                    }
+                }
+            }
+        }
+
+        if self.seed_btn.in_bound(raw_x, raw_y, w, h) {
+            let default = self.seed.map(|seed| seed.to_string());
+            let new_seed = Self::input_dialog("Enter Seed (blank for random)", default);
+            println!("{:?}", new_seed);
+            if let Some(mut new_seed) = new_seed {
+                new_seed.retain(|c| c.is_numeric());
+                if new_seed.is_empty() {
+                    self.seed = None;
+                } else {
+                    match new_seed.parse::<u64>() {
+                        Ok(seed) => {self.seed = Some(seed);},
+                        Err(e) => {println!("{:?}", e);}
+                    }
+                }
+            }
+        }
+
         TitleEvent::NoEvent
     }
 
     pub fn mouse_move(&mut self, raw_x: u32, raw_y: u32, w: u32, h: u32) {
        self.play_btn.mouse_move(raw_x, raw_y, w, h);
+       self.daily_btn.mouse_move(raw_x, raw_y, w, h);
+       self.continue_btn.mouse_move(raw_x, raw_y, w, h);
        self.file_choose_btn.mouse_move(raw_x, raw_y, w, h);
+       self.load_btn.mouse_move(raw_x, raw_y, w, h);
+       self.leaderboard_btn.mouse_move(raw_x, raw_y, w, h);
        self.width_btn.mouse_move(raw_x, raw_y, w, h);
        self.height_btn.mouse_move(raw_x, raw_y, w, h);
+       self.blanks_btn.mouse_move(raw_x, raw_y, w, h);
+       self.goal_btn.mouse_move(raw_x, raw_y, w, h);
+       self.variant_btn.mouse_move(raw_x, raw_y, w, h);
+       self.shuffle_btn.mouse_move(raw_x, raw_y, w, h);
+       self.seed_btn.mouse_move(raw_x, raw_y, w, h);
+       self.metric_btn.mouse_move(raw_x, raw_y, w, h);
+       self.key_mode_btn.mouse_move(raw_x, raw_y, w, h);
+       self.name_btn.mouse_move(raw_x, raw_y, w, h);
+       self.limit_btn.mouse_move(raw_x, raw_y, w, h);
+       self.fit_btn.mouse_move(raw_x, raw_y, w, h);
+       self.speed_btn.mouse_move(raw_x, raw_y, w, h);
+    }
+
+    //whoever is logged in, until they pick a name
+    fn default_name() -> String {
+        env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .unwrap_or_else(|_| "Player".to_string())
     }
 
-    pub fn new(hs : u32) -> Self {
-        Title {
+    pub fn new() -> Self {
+        let mut title = Title {
             grid_w: 5,
             grid_h: 5,
+            blanks: 1,
+            goal: GoalLayout::RowMajor,
+            variant: Variant::Slide,
             grid_img_path: "./res/sample.jpg".to_string(),
-            high_score: hs,
+            fit: FitMode::Letterbox,
+            shuffle: ShuffleMode::Uniform,
+            metric: MoveMetric::Mtm,
+            key_mode: KeyMode::Tile,
+            speed: SlideSpeed::Normal,
+            seed: None,
+            player_name: Title::default_name(),
+            move_limit: None,
+            high_score: None,
+            best_time: None,
+            daily_result: None,
+            daily_streak: 0,
+            can_continue: false,
             logo: opengl_graphics::Texture::from_path(
                 "./res/logo.png",
                 &opengl_graphics::TextureSettings::new()
                 ).unwrap(),
             play_btn: Button {
                 pos: ButtonPos::Centered(145),
-                w: 140,
+                w: 120,
                 h: 40,
                 label: "Play Game!".to_string(),
                 state: ButtonState::Normal
             },
+            daily_btn: Button {
+                pos: ButtonPos::Centered(145),
+                w: 120,
+                h: 40,
+                label: "Daily".to_string(),
+                state: ButtonState::Normal
+            },
+            continue_btn: Button {
+                pos: ButtonPos::CenteredOffset((130, 145)),
+                w: 120,
+                h: 40,
+                label: "Continue".to_string(),
+                state: ButtonState::Normal
+            },
             file_choose_btn: Button {
-                pos: ButtonPos::Centered(195),
-                w: 160,
+                pos: ButtonPos::CenteredOffset((-130, 195)),
+                w: 120,
                 h: 40,
                 label: "Choose Image".to_string(),
                 state: ButtonState::Normal
             },
+            load_btn: Button {
+                pos: ButtonPos::CenteredOffset((0, 195)),
+                w: 120,
+                h: 40,
+                label: "Load Board".to_string(),
+                state: ButtonState::Normal
+            },
+            leaderboard_btn: Button {
+                pos: ButtonPos::CenteredOffset((130, 195)),
+                w: 120,
+                h: 40,
+                label: "Leaderboard".to_string(),
+                state: ButtonState::Normal
+            },
             width_btn: Button {
-                pos: ButtonPos::CenteredOffset((-25, 245)),
-                w: 40,
+                pos: ButtonPos::CenteredOffset((-180, 245)),
+                w: 28,
                 h: 40,
                 label: "W".to_string(),
                 state: ButtonState::Normal
             },
+            blanks_btn: Button {
+                pos: ButtonPos::CenteredOffset((-120, 245)),
+                w: 28,
+                h: 40,
+                label: "B".to_string(),
+                state: ButtonState::Normal
+            },
+            variant_btn: Button {
+                pos: ButtonPos::CenteredOffset((-60, 245)),
+                w: 28,
+                h: 40,
+                label: "V".to_string(),
+                state: ButtonState::Normal
+            },
+            goal_btn: Button {
+                pos: ButtonPos::CenteredOffset((-90, 245)),
+                w: 28,
+                h: 40,
+                label: "G".to_string(),
+                state: ButtonState::Normal
+            },
             height_btn: Button {
-                pos: ButtonPos::CenteredOffset((25,245)),
-                w: 40,
+                pos: ButtonPos::CenteredOffset((-150, 245)),
+                w: 28,
                 h: 40,
                 label: "H".to_string(),
                 state: ButtonState::Normal
+            },
+            shuffle_btn: Button {
+                pos: ButtonPos::CenteredOffset((-30, 245)),
+                w: 28,
+                h: 40,
+                label: "S".to_string(),
+                state: ButtonState::Normal
+            },
+            seed_btn: Button {
+                pos: ButtonPos::CenteredOffset((0, 245)),
+                w: 28,
+                h: 40,
+                label: "#".to_string(),
+                state: ButtonState::Normal
+            },
+            metric_btn: Button {
+                pos: ButtonPos::CenteredOffset((30, 245)),
+                w: 28,
+                h: 40,
+                label: "M".to_string(),
+                state: ButtonState::Normal
+            },
+            key_mode_btn: Button {
+                pos: ButtonPos::CenteredOffset((60, 245)),
+                w: 28,
+                h: 40,
+                label: "K".to_string(),
+                state: ButtonState::Normal
+            },
+            name_btn: Button {
+                pos: ButtonPos::CenteredOffset((90, 245)),
+                w: 28,
+                h: 40,
+                label: "N".to_string(),
+                state: ButtonState::Normal
+            },
+            fit_btn: Button {
+                pos: ButtonPos::CenteredOffset((150, 245)),
+                w: 28,
+                h: 40,
+                label: "F".to_string(),
+                state: ButtonState::Normal
+            },
+            limit_btn: Button {
+                pos: ButtonPos::CenteredOffset((120, 245)),
+                w: 28,
+                h: 40,
+                label: "L".to_string(),
+                state: ButtonState::Normal
+            },
+            speed_btn: Button {
+                pos: ButtonPos::CenteredOffset((180, 245)),
+                w: 28,
+                h: 40,
+                label: "A".to_string(),
+                state: ButtonState::Normal
             }
-        }
+        };
+
+        title.refresh_continue();
+        title
     }
 }